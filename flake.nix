@@ -41,10 +41,11 @@
 
           postInstall = ''
             mkdir -p $out/share/beerio
-            cp -r assets missions.json $out/share/beerio/
+            cp -r assets missions.json catalog.json $out/share/beerio/
 
+            # Only a fallback, so a config file's paths still win over the bundled data
             wrapProgram $out/bin/beerio \
-              --run "cd $out/share/beerio"
+              --set-default BEERIO_SHARE_DIR $out/share/beerio
           '';
         };
 
@@ -23,6 +23,24 @@ in
       description = "Port to listen on";
     };
 
+    host = lib.mkOption {
+      type = lib.types.str;
+      default = "0.0.0.0";
+      description = "Address to listen on";
+    };
+
+    missionsFile = lib.mkOption {
+      type = lib.types.nullOr lib.types.path;
+      default = null;
+      description = "Override the missions.json shipped with the package";
+    };
+
+    logLevel = lib.mkOption {
+      type = lib.types.enum [ "trace" "debug" "info" "warn" "error" ];
+      default = "info";
+      description = "Log level";
+    };
+
     domain = lib.mkOption {
       type = lib.types.nullOr lib.types.str;
       default = null;
@@ -56,10 +74,16 @@ in
       after = [ "network.target" ];
       environment = {
         PORT = toString cfg.port;
+        BEERIO_HOST = cfg.host;
+        BEERIO_LOG = cfg.logLevel;
+        BEERIO_DATA = "/var/lib/beerio";
+      } // lib.optionalAttrs (cfg.missionsFile != null) {
+        BEERIO_MISSIONS = toString cfg.missionsFile;
       };
       serviceConfig = {
         ExecStart = "${cfg.package}/bin/beerio";
         Restart = "always";
+        StateDirectory = "beerio";
         User = "marty"; # Running as user marty, or create a dedicated user
       };
     };
@@ -0,0 +1,108 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::model::Catalog;
+
+const IMAGE_EXTENSIONS: &[&str] = &["webp", "png", "jpg", "jpeg", "gif", "svg"];
+
+/// Differences between catalog.json and the image files under the assets directory.
+/// All paths are relative to the assets directory.
+#[derive(Debug, Default)]
+pub struct AssetReport {
+    /// (part name, expected image) with no file and no likely candidate
+    pub missing: Vec<(String, PathBuf)>,
+    /// (part name, expected image, file that's probably meant to be it)
+    pub misnamed: Vec<(String, PathBuf, PathBuf)>,
+    /// Images in a catalog directory that nothing references
+    pub orphaned: Vec<PathBuf>,
+}
+
+impl AssetReport {
+    /// Missing or misnamed images break pages, orphans are only clutter.
+    pub fn has_errors(&self) -> bool {
+        !self.missing.is_empty() || !self.misnamed.is_empty()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        !self.has_errors() && self.orphaned.is_empty()
+    }
+}
+
+impl fmt::Display for AssetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, expected) in &self.missing {
+            writeln!(f, "missing   {} ({})", expected.display(), name)?;
+        }
+        for (name, expected, found) in &self.misnamed {
+            writeln!(f, "misnamed  {} (expected {} for {})", found.display(), expected.display(), name)?;
+        }
+        for path in &self.orphaned {
+            writeln!(f, "orphaned  {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+pub fn check(catalog: &Catalog, assets_dir: &Path) -> AssetReport {
+    let parts = catalog.characters.iter()
+        .chain(&catalog.karts)
+        .chain(&catalog.wheels)
+        .chain(&catalog.gliders)
+        .chain(&catalog.items);
+
+    let mut referenced = BTreeMap::new();
+    for part in parts {
+        referenced.insert(PathBuf::from(&part.image), part.name.clone());
+    }
+
+    // Only the directories the catalog points into are scanned, so style.css and friends are left alone
+    let dirs: BTreeSet<PathBuf> = referenced.keys()
+        .map(|p| p.parent().unwrap_or(Path::new("")).to_path_buf())
+        .collect();
+    let mut unreferenced: BTreeSet<PathBuf> = BTreeSet::new();
+    for dir in &dirs {
+        let Ok(entries) = fs::read_dir(assets_dir.join(dir)) else { continue };
+        for entry in entries.flatten() {
+            let path = dir.join(entry.file_name());
+            if is_image(&path) && !referenced.contains_key(&path) {
+                unreferenced.insert(path);
+            }
+        }
+    }
+
+    let mut report = AssetReport::default();
+    for (image, name) in referenced {
+        if assets_dir.join(&image).is_file() {
+            continue;
+        }
+        let candidate = unreferenced.iter()
+            .find(|found| found.parent() == image.parent() && normalized_stem(found) == normalized_stem(&image))
+            .cloned();
+        match candidate {
+            Some(found) => {
+                unreferenced.remove(&found);
+                report.misnamed.push((name, image, found));
+            }
+            None => report.missing.push((name, image)),
+        }
+    }
+    report.orphaned = unreferenced.into_iter().collect();
+    report
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+// "Bowser_Jr..webp" and "bowser_jr.png" both become "bowserjr"
+fn normalized_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect()
+}
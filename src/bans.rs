@@ -0,0 +1,120 @@
+use axum::{
+    extract::{Path, State},
+    response::{Html, IntoResponse},
+    routing::{get, post},
+    http::HeaderMap,
+    Router,
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use askama::Template;
+
+use crate::filter::{FilterQuery, MissionFilter};
+use crate::render_response;
+use crate::state::AppState;
+
+// Bans live in the browser rather than on the server, so they follow whoever
+// chose them across seeds and restarts without any accounts. Pages bake them
+// into the links they hand out, so a shared link draws the same everywhere.
+const BANS_COOKIE: &str = "beerio_bans";
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/bans", get(bans_view))
+        .route("/bans/{name}", post(ban_toggle))
+}
+
+/// Mission names this session never wants drawn.
+pub fn banned(jar: &CookieJar) -> Vec<String> {
+    let Some(cookie) = jar.get(BANS_COOKIE) else { return Vec::new() };
+    cookie.value().split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| percent_encoding::percent_decode_str(name).decode_utf8_lossy().into_owned())
+        .collect()
+}
+
+/// The filter a link asks for. Links without their own ban list get this session's.
+pub fn filter(query: &FilterQuery, jar: &CookieJar) -> MissionFilter {
+    let mut filter = query.filter();
+    if query.ban.is_none() {
+        filter.banned = banned(jar);
+    }
+    filter
+}
+
+// Each name is encoded on its own so commas in names can't split them
+fn store(jar: CookieJar, names: &[String]) -> CookieJar {
+    let value = names.iter()
+        .map(|name| percent_encoding::utf8_percent_encode(name, percent_encoding::NON_ALPHANUMERIC).to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let cookie = Cookie::build((BANS_COOKIE, value))
+        .path("/")
+        .same_site(SameSite::Lax)
+        .permanent();
+    jar.add(cookie)
+}
+
+#[derive(Template)]
+#[template(path = "bans.html")]
+struct BansTemplate {
+    banned: Vec<String>,
+}
+
+async fn bans_view(jar: CookieJar, headers: HeaderMap) -> Html<String> {
+    let template = BansTemplate { banned: banned(&jar) };
+    render_response(headers, template.render().unwrap(), None)
+}
+
+// Bans or unbans, then has the page reload so the draw reflects it
+async fn ban_toggle(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    jar: CookieJar,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let mut names = banned(&jar);
+    toggle(&state, &mut names, &name);
+    let jar = store(jar, &names);
+
+    // A page whose link carries its own ban list has to get a new link, or the
+    // reload would draw the same thing again
+    let current = headers.get("HX-Current-URL").and_then(|url| url.to_str().ok());
+    match current.and_then(|url| toggled_link(&state, url, &name)) {
+        Some(link) => (jar, [("HX-Redirect", link)]).into_response(),
+        None => (jar, [("HX-Refresh", "true")]).into_response(),
+    }
+}
+
+fn toggle(state: &AppState, names: &mut Vec<String>, name: &str) {
+    match names.iter().position(|n| n.eq_ignore_ascii_case(name)) {
+        Some(pos) => {
+            names.remove(pos);
+        }
+        // Only real missions, so the list can't be stuffed with junk
+        None => {
+            if let Some(mission) = crate::find_mission(&state.missions(), name) {
+                names.push(mission.name);
+            }
+        }
+    }
+}
+
+// `url` with the name toggled in its `ban` parameter. None if it has none.
+fn toggled_link(state: &AppState, url: &str, name: &str) -> Option<String> {
+    let (base, query) = url.split_once('?')?;
+    let mut found = false;
+    let params: Vec<String> = query.split('&')
+        .map(|param| match param.strip_prefix("ban=") {
+            Some(value) => {
+                found = true;
+                let value = value.replace('+', " ");
+                let value = percent_encoding::percent_decode_str(&value).decode_utf8_lossy();
+                let mut filter = FilterQuery { ban: Some(value.into_owned()), ..FilterQuery::default() }.filter();
+                toggle(state, &mut filter.banned, name);
+                format!("ban={}", percent_encoding::utf8_percent_encode(&filter.banned.join(","), percent_encoding::NON_ALPHANUMERIC))
+            }
+            None => param.to_string(),
+        })
+        .collect();
+    found.then(|| format!("{}?{}", base, params.join("&")))
+}
@@ -0,0 +1,86 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    http::StatusCode,
+    Router,
+};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use askama::Template;
+use serde::Deserialize;
+use std::convert::Infallible;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt;
+
+use crate::model::{Catalog, Category, Checklist, ChecklistState};
+use crate::state::AppState;
+
+// Checklists on plain seeded pages. Lobbies keep their own, see traitor.rs.
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/{seed}/checklist/sse", get(checklist_sse))
+        .route("/{seed}/checklist/{category}", get(checklist_view))
+        .route("/{seed}/checklist/{category}/{slug}", post(checklist_toggle))
+}
+
+// The goal lives on the mission, not with the ticks, so pages pass it along
+#[derive(Deserialize)]
+pub struct ChecklistQuery {
+    pub goal: Option<usize>,
+}
+
+#[derive(Template)]
+#[template(path = "partials/checklist_body.html")]
+struct ChecklistTemplate {
+    checklist: Checklist,
+}
+
+// Everyone opening the same seed shares one set of ticks
+pub fn url(seed: &str) -> String {
+    format!("/{}/checklist", seed)
+}
+
+/// SSE event telling pages to re-fetch the checklist for `category`.
+pub fn event_name(category: Category) -> String {
+    format!("checklist_{}", category.slug())
+}
+
+/// The checklist for `category` as everyone sharing `url` sees it right now.
+pub fn render(catalog: &Catalog, category: Category, goal: Option<usize>, url: String, ticked: &ChecklistState) -> String {
+    let mut checklist = Checklist::new(category, goal, catalog);
+    checklist.url = url;
+    checklist.done = ticked.get(&category).cloned().unwrap_or_default();
+    ChecklistTemplate { checklist }.render().unwrap()
+}
+
+async fn checklist_view(
+    State(state): State<AppState>,
+    Path((seed, category)): Path<(String, Category)>,
+    Query(query): Query<ChecklistQuery>,
+) -> Html<String> {
+    Html(render(&state.catalog(), category, query.goal, url(&seed), &state.seed_checklists(&seed)))
+}
+
+async fn checklist_toggle(
+    State(state): State<AppState>,
+    Path((seed, category, slug)): Path<(String, Category, String)>,
+    Query(query): Query<ChecklistQuery>,
+) -> Response {
+    let catalog = state.catalog();
+    if !Checklist::new(category, None, &catalog).contains(&slug) {
+        return (StatusCode::NOT_FOUND, "Not on this checklist").into_response();
+    }
+    let ticked = state.toggle_seed_checklist(&seed, category, &slug);
+    Html(render(&catalog, category, query.goal, url(&seed), &ticked)).into_response()
+}
+
+async fn checklist_sse(
+    State(state): State<AppState>,
+    Path(seed): Path<String>,
+) -> impl IntoResponse {
+    let stream = BroadcastStream::new(state.subscribe_seed_checklist(&seed)).map(|msg| match msg {
+        Ok(category) => Ok::<Event, Infallible>(Event::default().event(event_name(category)).data("updated")),
+        Err(_) => Ok::<Event, Infallible>(Event::default()), // Ignore lag errors
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
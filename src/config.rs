@@ -0,0 +1,187 @@
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+
+// Precedence is CLI flag > environment variable > config file > default.
+// `PORT` is unprefixed because that's what the nix module sets.
+#[derive(Parser, Debug)]
+#[command(version, about = "Beerio Kart mission randomizer")]
+pub struct Cli {
+    /// Optional TOML config file
+    #[arg(long, short, env = "BEERIO_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Address to listen on
+    #[arg(long, env = "BEERIO_HOST")]
+    pub host: Option<IpAddr>,
+
+    /// Port to listen on
+    #[arg(long, short, env = "PORT")]
+    pub port: Option<u16>,
+
+    /// Path to missions.json
+    #[arg(long, env = "BEERIO_MISSIONS")]
+    pub missions: Option<PathBuf>,
+
+    /// Path to catalog.json [default: next to the missions file]
+    #[arg(long, env = "BEERIO_CATALOG")]
+    pub catalog: Option<PathBuf>,
+
+    /// Directory served under /assets
+    #[arg(long, env = "BEERIO_ASSETS")]
+    pub assets: Option<PathBuf>,
+
+    /// Where missions.json, catalog.json and assets/ are installed, used for
+    /// whichever of them nothing else sets [default: the working directory]
+    #[arg(long, env = "BEERIO_SHARE_DIR")]
+    pub share_dir: Option<PathBuf>,
+
+    /// Where traitor lobbies are kept between restarts
+    #[arg(long, env = "BEERIO_LOBBY_STORE")]
+    pub lobby_store: Option<LobbyStoreKind>,
+
+    /// Directory for persistent state such as lobbies
+    #[arg(long, env = "BEERIO_DATA")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Close lobbies with no activity and no connections for this many seconds [default: 7200]
+    #[arg(long, env = "BEERIO_LOBBY_IDLE_TTL")]
+    pub lobby_idle_ttl: Option<u64>,
+
+    /// Close lobbies this many seconds after creation, active or not [default: 86400]
+    #[arg(long, env = "BEERIO_LOBBY_MAX_AGE")]
+    pub lobby_max_age: Option<u64>,
+
+    /// Seconds a player whose connection dropped stays in the lobby before leaving it [default: 30]
+    #[arg(long, env = "BEERIO_RECONNECT_GRACE")]
+    pub reconnect_grace: Option<u64>,
+
+    /// Secret callers of /admin/reload send as `Authorization: Bearer <token>`.
+    /// The endpoint is disabled without one.
+    #[arg(long, env = "BEERIO_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+
+    /// trace, debug, info, warn or error
+    #[arg(long, env = "BEERIO_LOG")]
+    pub log_level: Option<String>,
+
+    /// Refuse to start when any catalog image is missing, misnamed or orphaned
+    #[arg(long, env = "BEERIO_STRICT")]
+    pub strict: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LobbyStoreKind {
+    /// JSON files under the data directory
+    File,
+    /// Nothing survives a restart
+    Memory,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Check catalog images against the assets directory and exit
+    Check,
+    /// Validate missions.json against the catalog and exit
+    Validate,
+}
+
+// Relative paths in the file are resolved against the file's own directory
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    host: Option<IpAddr>,
+    port: Option<u16>,
+    missions: Option<PathBuf>,
+    catalog: Option<PathBuf>,
+    assets: Option<PathBuf>,
+    lobby_store: Option<LobbyStoreKind>,
+    data_dir: Option<PathBuf>,
+    lobby_idle_ttl: Option<u64>,
+    lobby_max_age: Option<u64>,
+    reconnect_grace: Option<u64>,
+    admin_token: Option<String>,
+    log_level: Option<String>,
+    strict: Option<bool>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub host: IpAddr,
+    pub port: u16,
+    pub missions_path: PathBuf,
+    pub catalog_path: PathBuf,
+    pub assets_dir: PathBuf,
+    pub lobby_store: LobbyStoreKind,
+    pub data_dir: PathBuf,
+    pub lobby_idle_ttl: Duration,
+    pub lobby_max_age: Duration,
+    pub reconnect_grace: Duration,
+    pub admin_token: Option<String>,
+    pub log_level: tracing::Level,
+    pub strict: bool,
+    pub command: Option<Command>,
+}
+
+impl Config {
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_cli(Cli::parse())
+    }
+
+    pub fn from_cli(cli: Cli) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = match &cli.config {
+            Some(path) => {
+                let content = fs::read_to_string(path)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                let mut file: FileConfig = toml::from_str(&content)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                let base = path.parent().unwrap_or(Path::new(""));
+                file.missions = file.missions.map(|p| base.join(p));
+                file.catalog = file.catalog.map(|p| base.join(p));
+                file.assets = file.assets.map(|p| base.join(p));
+                file.data_dir = file.data_dir.map(|p| base.join(p));
+                file
+            }
+            None => FileConfig::default(),
+        };
+
+        let log_level = cli.log_level.or(file.log_level).unwrap_or_else(|| "info".to_string());
+        let log_level = log_level
+            .parse()
+            .map_err(|_| format!("Invalid log level '{}'", log_level))?;
+
+        // Packages point this at their bundled data, below anything the user sets
+        let share_dir = cli.share_dir.unwrap_or_default();
+        let missions_path = cli.missions.or(file.missions).unwrap_or_else(|| share_dir.join("missions.json"));
+        let catalog_path = cli.catalog.or(file.catalog)
+            .unwrap_or_else(|| missions_path.with_file_name("catalog.json"));
+
+        Ok(Self {
+            host: cli.host.or(file.host).unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            port: cli.port.or(file.port).unwrap_or(3000),
+            missions_path,
+            catalog_path,
+            assets_dir: cli.assets.or(file.assets).unwrap_or_else(|| share_dir.join("assets")),
+            lobby_store: cli.lobby_store.or(file.lobby_store).unwrap_or(LobbyStoreKind::File),
+            data_dir: cli.data_dir.or(file.data_dir).unwrap_or_else(|| PathBuf::from("data")),
+            lobby_idle_ttl: Duration::from_secs(cli.lobby_idle_ttl.or(file.lobby_idle_ttl).unwrap_or(2 * 60 * 60)),
+            lobby_max_age: Duration::from_secs(cli.lobby_max_age.or(file.lobby_max_age).unwrap_or(24 * 60 * 60)),
+            reconnect_grace: Duration::from_secs(cli.reconnect_grace.or(file.reconnect_grace).unwrap_or(30)),
+            admin_token: cli.admin_token.or(file.admin_token).filter(|token| !token.is_empty()),
+            log_level,
+            strict: cli.strict || file.strict.unwrap_or(false),
+            command: cli.command,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+}
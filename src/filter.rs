@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Difficulty, Mission, MissionsData};
+
+/// Narrows down which missions can be drawn. The default lets everything through,
+/// and filtering never reorders a pool, so seeded draws only change when the
+/// filter actually removes something.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MissionFilter {
+    /// Missions need every one of these tags
+    pub tags: Vec<String>,
+    /// Missions with any of these tags are left out
+    pub exclude: Vec<String>,
+    /// Hardest difficulty allowed. Missions without a difficulty always pass.
+    pub max_difficulty: Option<Difficulty>,
+    /// Size of the table. Missions without a player count always pass.
+    pub players: Option<usize>,
+    /// Mission names that are never drawn. Links carry them so everyone opening
+    /// one draws from the same pool, see `bans.rs` for where they come from.
+    pub banned: Vec<String>,
+}
+
+/// The filter as it appears in seed URLs and settings forms. Lists are comma
+/// separated; anything that doesn't parse is ignored rather than rejected.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct FilterQuery {
+    pub tags: String,
+    pub exclude: String,
+    pub max_difficulty: String,
+    pub player_count: String,
+    /// Comma separated mission names. Missing means the session's own bans apply.
+    pub ban: Option<String>,
+}
+
+impl FilterQuery {
+    pub fn filter(&self) -> MissionFilter {
+        MissionFilter {
+            tags: tag_list(&self.tags),
+            exclude: tag_list(&self.exclude),
+            max_difficulty: Difficulty::from_slug(self.max_difficulty.trim()),
+            players: self.player_count.trim().parse().ok().filter(|&n| n > 0),
+            banned: self.ban.as_deref().map(name_list).unwrap_or_default(),
+        }
+    }
+}
+
+fn name_list(raw: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in raw.split(',').map(str::trim) {
+        if !name.is_empty() && !names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+fn tag_list(raw: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in raw.split(',').map(|t| t.trim().to_lowercase()) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+impl MissionFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn allows(&self, mission: &Mission) -> bool {
+        let has = |tag: &String| mission.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+        self.tags.iter().all(has)
+            && !self.exclude.iter().any(has)
+            && self.max_difficulty.is_none_or(|max| mission.difficulty.is_none_or(|d| d <= max))
+            && self.players.is_none_or(|n| mission.player_count.is_none_or(|c| c.fits(n)))
+            && !self.banned.iter().any(|name| name.eq_ignore_ascii_case(&mission.name))
+    }
+
+    /// `data` with every pool narrowed down, nested draws included.
+    pub fn apply(&self, data: &MissionsData) -> MissionsData {
+        let keep = |pool: &[Mission]| pool.iter().filter(|m| self.allows(m)).cloned().collect();
+        MissionsData {
+            missions: keep(&data.missions),
+            coop_granprix: keep(&data.coop_granprix),
+            coop_single: keep(&data.coop_single),
+            traitor_objectives: keep(&data.traitor_objectives),
+        }
+    }
+
+    /// Query string for links that should draw from the same filtered pools,
+    /// `&`-separated without a leading `?`. Empty for the default filter.
+    pub fn query(&self) -> String {
+        let encode = |s: &str| percent_encoding::utf8_percent_encode(s, percent_encoding::NON_ALPHANUMERIC).to_string();
+        let mut params = Vec::new();
+        if !self.tags.is_empty() {
+            params.push(format!("tags={}", encode(&self.tags.join(","))));
+        }
+        if !self.exclude.is_empty() {
+            params.push(format!("exclude={}", encode(&self.exclude.join(","))));
+        }
+        if let Some(max) = self.max_difficulty {
+            params.push(format!("max_difficulty={}", max.slug()));
+        }
+        if let Some(players) = self.players {
+            params.push(format!("player_count={}", players));
+        }
+        if !self.banned.is_empty() {
+            params.push(format!("ban={}", encode(&self.banned.join(","))));
+        }
+        params.join("&")
+    }
+
+    /// Short summary for the lobby, e.g. "offline, no hard missions".
+    pub fn describe(&self) -> String {
+        let mut parts: Vec<String> = self.tags.clone();
+        parts.extend(self.exclude.iter().map(|t| format!("no {}", t)));
+        if let Some(max) = self.max_difficulty {
+            parts.push(format!("up to {}", max.slug()));
+        }
+        if !self.banned.is_empty() {
+            parts.push(format!("{} banned", self.banned.len()));
+        }
+        parts.join(", ")
+    }
+}
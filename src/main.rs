@@ -1,36 +1,66 @@
 use axum::{
-    extract::{State, Query, Form},
-    response::{Html, Redirect, IntoResponse},
+    extract::State,
+    response::{Html, IntoResponse},
     routing::{get, post},
     http::{HeaderMap, StatusCode},
     Router,
 };
-use axum::response::Response;
-use serde::Deserialize;
+use axum_extra::extract::cookie::CookieJar;
 use tower_http::services::ServeDir;
 use std::sync::Arc;
 use askama::Template;
-use rand::SeedableRng;
-use rand::rngs::StdRng;
-use rand::seq::SliceRandom;
-use rand::Rng;
-use axum::response::sse::{Event, Sse};
-use tokio::sync::broadcast;
-use tokio_stream::wrappers::BroadcastStream;
-use tokio_stream::StreamExt;
-use std::convert::Infallible;
 
+mod assets;
+mod bans;
+mod checklist;
+mod config;
+mod filter;
 mod model;
+mod seed;
 mod state;
+mod store;
+mod traitor;
+mod validate;
 
-use state::{AppState, Lobby, Player, LobbyStatus, LobbyEvent};
-use model::{MissionsData, Mission};
+use filter::{FilterQuery, MissionFilter};
+use state::AppState;
+use model::{Difficulty, MissionsData, Mission, MissionPool, Loadout, Resolved, ResolvedMission};
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt::init();
+    let config = config::Config::load().unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {}", e);
+        std::process::exit(2);
+    });
+    tracing_subscriber::fmt().with_max_level(config.log_level).init();
+
+    if let Some(command) = &config.command {
+        std::process::exit(run_command(command, &config));
+    }
 
-    let state = AppState::new().expect("Failed to load missions");
+    if let Err(e) = check_assets(&config) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    let store: Arc<dyn store::LobbyStore> = match config.lobby_store {
+        config::LobbyStoreKind::File => {
+            let dir = config.data_dir.join("lobbies");
+            Arc::new(store::FileStore::new(dir).unwrap_or_else(|e| {
+                eprintln!("Can't use data directory {}: {}", config.data_dir.display(), e);
+                std::process::exit(1);
+            }))
+        }
+        config::LobbyStoreKind::Memory => Arc::new(store::MemoryStore),
+    };
+
+    let state = AppState::new(config.missions_path.clone(), config.catalog_path.clone(), store, config.reconnect_grace, config.admin_token.clone()).unwrap_or_else(|e| {
+        eprintln!("Failed to load missions:\n{}", e);
+        std::process::exit(1);
+    });
+    state.watch_missions();
+    state.spawn_reaper(config.lobby_idle_ttl, config.lobby_max_age);
+    traitor::spawn_grace_timers(&state);
 
     let app = Router::new()
         .route("/", get(root))
@@ -41,25 +71,85 @@ async fn main() {
         .route("/all_missions", get(all_missions))
 
         .route("/{seed}/mission/{name}", get(mission_view))
-        .route("/traitor/create", get(traitor_setup).post(traitor_create))
-        .route("/traitor/{room_id}/join", get(traitor_join_view).post(traitor_join_action))
-        .route("/traitor/{room_id}", get(traitor_lobby_view))
-        .route("/traitor/{room_id}/sse", get(traitor_lobby_sse))
-        .route("/traitor/{room_id}/start", post(traitor_start))
-        .route("/traitor/{room_id}/name/{old_name}", post(traitor_change_name_action))
-        .route("/traitor/{room_id}/role", get(traitor_role_view))
-        .nest_service("/assets", ServeDir::new("assets"))
+        .route("/admin/reload", post(admin_reload))
+        .route("/metrics", get(metrics))
+        .merge(bans::routes())
+        .merge(checklist::routes())
+        .merge(traitor::routes())
+        .nest_service("/assets", ServeDir::new(&config.assets_dir))
         .with_state(state);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    println!("Listening on http://0.0.0.0:3000");
+    let listener = tokio::net::TcpListener::bind(config.addr()).await.unwrap();
+    println!("Listening on http://{}", config.addr());
     axum::serve(listener, app).await.unwrap();
 }
 
+fn run_command(command: &config::Command, config: &config::Config) -> i32 {
+    match command {
+        config::Command::Check => {
+            let catalog = match state::load_catalog(&config.catalog_path) {
+                Ok(catalog) => catalog,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+            };
+            let report = assets::check(&catalog, &config.assets_dir);
+            print!("{}", report);
+            if report.has_errors() || (config.strict && !report.is_clean()) {
+                1
+            } else {
+                if report.is_clean() {
+                    println!("Assets OK");
+                }
+                0
+            }
+        }
+        config::Command::Validate => {
+            let result = state::load_catalog(&config.catalog_path).and_then(|catalog| {
+                let source = std::fs::read_to_string(&config.missions_path)?;
+                Ok(validate::validate(&source, &catalog))
+            });
+            match result {
+                Ok(Ok(_)) => {
+                    println!("{} OK", config.missions_path.display());
+                    0
+                }
+                Ok(Err(issues)) => {
+                    for issue in &issues {
+                        println!("{}: {}", config.missions_path.display(), issue);
+                    }
+                    1
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    1
+                }
+            }
+        }
+    }
+}
+
+// Problems are only logged unless running strict
+fn check_assets(config: &config::Config) -> Result<(), String> {
+    let catalog = state::load_catalog(&config.catalog_path).map_err(|e| e.to_string())?;
+    let report = assets::check(&catalog, &config.assets_dir);
+    if report.is_clean() {
+        return Ok(());
+    }
+    if config.strict {
+        return Err(format!("Asset check failed (strict):\n{}", report));
+    }
+    for line in report.to_string().lines() {
+        tracing::warn!("{}", line);
+    }
+    Ok(())
+}
+
 // IndexTemplate removed as it was unused and caused compilation errors (missing seed)
 
 async fn root() -> axum::response::Redirect {
-    let seed = rand::thread_rng().gen::<u32>().to_string();
+    let seed = seed::generate();
     axum::response::Redirect::to(&format!("/{}/solo", seed))
 }
 
@@ -74,7 +164,7 @@ fn render_response(headers: HeaderMap, content: String, seed: Option<String>) ->
     if headers.contains_key("hx-request") {
         Html(content)
     } else {
-        let seed = seed.unwrap_or_else(|| rand::thread_rng().gen::<u32>().to_string());
+        let seed = seed.unwrap_or_else(seed::generate);
         let wrapper = LayoutWrapperTemplate { content, seed };
         Html(wrapper.render().unwrap())
     }
@@ -90,183 +180,189 @@ async fn seed_redirect(
 #[derive(Template)]
 #[template(path = "partials/coop.html")]
 struct CoopTemplate {
-    mission: Mission,
-    // We might need to pass nested data for complex missions
-    nested_mission: Option<Mission>,
-    random_item_img: Option<String>,
-    random_loadout: Option<Loadout>,
-    random_number: Option<u32>,
-    nested_gacha_items: Option<Vec<String>>,
+    // None when the filter leaves nothing to draw
+    data: Option<ResolvedMission>,
+    action: String,
+    filter: MissionFilter,
+    banned: usize,
     view_name: String,
 }
 
-#[derive(Clone)] // Added Clone here
-struct Loadout {
-    char_img: String,
-    char_name: String,
-    kart_img: String,
-    kart_name: String,
-    wheel_img: String,
-    wheel_name: String,
-    glider_img: String,
-    glider_name: String,
-}
-
-struct MissionDisplayData {
-    mission: Mission,
-    random_item_img: Option<String>,
-    random_loadout: Option<Loadout>,
-    random_number: Option<u32>,
-    nested_mission: Option<Mission>,
-    nested_gacha_items: Option<Vec<String>>,
-}
-
-fn resolve_mission_display(mission: Mission, rng: &mut StdRng, state: &AppState) -> MissionDisplayData {
-    let random_item_img = if mission.needs_random_item {
-        let item = model::ITEMS.choose(rng).unwrap();
-        Some(format!("/assets/items/{}.png", item))
-    } else { None };
-
-    let random_loadout = if mission.needs_random_loadout {
-        let c = model::CHARACTERS.choose(rng).unwrap();
-        let k = model::KARTS.choose(rng).unwrap();
-        let w = model::WHEELS.choose(rng).unwrap();
-        let g = model::GLIDERS.choose(rng).unwrap();
-        
-        Some(Loadout {
-            char_img: format!("/assets/characters/{}.webp", c.to_lowercase().replace(" ", "_")),
-            char_name: c.to_string(),
-            kart_img: format!("/assets/karts/{}.webp", k.to_lowercase().replace(" ", "_")),
-            kart_name: k.to_string(),
-            wheel_img: format!("/assets/wheels/{}.webp", w.to_lowercase().replace(" ", "_")),
-            wheel_name: w.to_string(),
-            glider_img: format!("/assets/gliders/{}.webp", g.to_lowercase().replace(" ", "_")),
-            glider_name: g.to_string(),
-        })
-    } else { None };
-
-    let random_number = mission.needs_random_number.map(|max| rng.gen_range(1..=max));
-
-    let (nested_mission, nested_gacha_items) = if mission.needs_coop_singles {
-         let sub = state.missions.coop_single.choose(rng).cloned();
-         let gacha = sub.as_ref().and_then(|m| {
-             if m.needs_gacha_item_checklist {
-                 Some(model::GACHA_ITEMS.iter().map(|s| format!("/assets/items/{}.png", s)).collect())
-             } else { None }
-         });
-         (sub, gacha)
-    } else { (None, None) };
-
-    MissionDisplayData {
-        mission,
-        random_item_img,
-        random_loadout,
-        random_number,
-        nested_mission,
-        nested_gacha_items,
-    }
-}
-
 async fn coop(
     State(state): State<AppState>,
     axum::extract::Path(seed): axum::extract::Path<String>,
+    axum::extract::Query(filter): axum::extract::Query<FilterQuery>,
+    jar: CookieJar,
     headers: HeaderMap,
 ) -> Html<String> {
-    
-    // Create RNG from seed
-    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-    std::hash::Hash::hash(&seed, &mut hasher);
-    let seed_u64 = std::hash::Hasher::finish(&hasher);
-    let mut rng = StdRng::seed_from_u64(seed_u64);
-
-    let missions = &state.missions.coop_granprix;
-    let mission = missions.choose(&mut rng).unwrap().clone();
-    
-    // Resolve dynamic content
-    let random_item_img = if mission.needs_random_item {
-        let item = model::ITEMS.choose(&mut rng).unwrap();
-        Some(format!("/assets/items/{}.png", item))
-    } else {
-        None
-    };
-
-    let random_loadout = if mission.needs_random_loadout {
-        let c = model::CHARACTERS.choose(&mut rng).unwrap();
-        let k = model::KARTS.choose(&mut rng).unwrap();
-        let w = model::WHEELS.choose(&mut rng).unwrap();
-        let g = model::GLIDERS.choose(&mut rng).unwrap();
-        
-        Some(Loadout {
-            char_img: format!("/assets/characters/{}.webp", c.to_lowercase().replace(" ", "_")),
-            char_name: c.to_string(),
-            kart_img: format!("/assets/karts/{}.webp", k.to_lowercase().replace(" ", "_")),
-            kart_name: k.to_string(),
-            wheel_img: format!("/assets/wheels/{}.webp", w.to_lowercase().replace(" ", "_")),
-            wheel_name: w.to_string(),
-            glider_img: format!("/assets/gliders/{}.webp", g.to_lowercase().replace(" ", "_")),
-            glider_name: g.to_string(),
-        })
-    } else {
-        None
-    };
-
-    let random_number = mission.needs_random_number.map(|max| {
-         rng.gen_range(1..=max)
+    let mut rng = seed::rng(&seed);
+
+    let filter = bans::filter(&filter, &jar);
+    let missions = state.filtered_missions(&filter);
+    let data = model::draw_mission(&missions.coop_granprix, &mut rng).cloned().map(|mission| {
+        let mut data = model::resolve(mission, &mut rng, &missions, &state.catalog());
+        data.attach_checklists(&checklist::url(&seed), &state.seed_checklists(&seed));
+        data
     });
 
-    let (nested_mission, nested_gacha_items) = if mission.needs_coop_singles {
-         let sub = state.missions.coop_single.choose(&mut rng).cloned();
-         let gacha = sub.as_ref().and_then(|m| {
-             if m.needs_gacha_item_checklist {
-                 Some(model::GACHA_ITEMS.iter().map(|s| format!("/assets/items/{}.png", s)).collect())
-             } else { None }
-         });
-         (sub, gacha)
-    } else { (None, None) };
-
     let template = CoopTemplate {
-        mission,
-        nested_mission,
-        random_item_img,
-        random_loadout,
-        random_number,
-        nested_gacha_items,
+        data,
+        action: format!("/{}/coop", seed),
+        banned: filter.banned.len(),
+        filter,
         view_name: "coop".to_string(),
     };
 
     render_response(headers, template.render().unwrap(), Some(seed))
 }
 
+// Nested missions render through their own template so requirement.html can recurse
+#[derive(Template)]
+#[template(path = "partials/nested_mission.html")]
+struct NestedMissionTemplate<'a> {
+    label: &'a str,
+    data: &'a ResolvedMission,
+}
+
+fn render_nested(pool: &MissionPool, data: &ResolvedMission) -> String {
+    NestedMissionTemplate { label: pool.label(), data }.render().unwrap()
+}
+
 #[derive(Template)]
 #[template(path = "partials/solo.html")]
 struct SoloTemplate {
-    missions: Vec<MissionDisplayData>,
+    missions: Vec<ResolvedMission>,
+    // Filled in when dealing for a table instead
+    hands: Vec<Hand>,
+    // None where the mission count can't be changed, like a single mission's page
+    form: Option<SoloForm>,
     view_name: String,
 }
 
+struct SoloForm {
+    action: String,
+    count: usize,
+    players: String,
+    filter: MissionFilter,
+    banned: usize,
+    // Link back to the whole table from a single player's hand
+    table_link: Option<String>,
+    notice: Option<String>,
+}
+
+struct Hand {
+    name: String,
+    link: String,
+    missions: Vec<ResolvedMission>,
+}
+
+const DEFAULT_SOLO_COUNT: usize = 2;
+const MAX_TABLE: usize = 12;
+const MAX_PLAYER_NAME: usize = 20;
+
+#[derive(serde::Deserialize)]
+struct SoloQuery {
+    count: Option<usize>,
+    // Comma separated names. Each player gets their own missions, none dealt twice.
+    #[serde(default)]
+    players: String,
+    // Only show this player's hand, 1-based
+    seat: Option<usize>,
+}
+
 async fn solo(
     State(state): State<AppState>,
     axum::extract::Path(seed): axum::extract::Path<String>,
+    axum::extract::Query(query): axum::extract::Query<SoloQuery>,
+    axum::extract::Query(filter): axum::extract::Query<FilterQuery>,
+    jar: CookieJar,
     headers: HeaderMap,
 ) -> Html<String> {
-    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-    std::hash::Hash::hash(&seed, &mut hasher);
-    let seed_u64 = std::hash::Hasher::finish(&hasher);
-    let mut rng = StdRng::seed_from_u64(seed_u64);
-    
-    // Choose 2 unique missions
-    let amount = 2;
-    let chosen: Vec<_> = state.missions.missions
-        .choose_multiple(&mut rng, amount)
+    let mut rng = seed::rng(&seed);
+    let names: Vec<String> = query.players.split(',')
+        .map(|name| name.trim().chars().take(MAX_PLAYER_NAME).collect::<String>())
+        .filter(|name| !name.is_empty())
+        .take(MAX_TABLE)
+        .collect();
+    let players = names.join(",");
+    // A dealt table knows its own size, no need to put it in the link
+    let filter = bans::filter(&filter, &jar);
+    let mut draw = filter.clone();
+    if draw.players.is_none() && !names.is_empty() {
+        draw.players = Some(names.len());
+    }
+    let missions = state.filtered_missions(&draw);
+    let count = query.count.unwrap_or(DEFAULT_SOLO_COUNT).clamp(1, missions.missions.len().max(1));
+
+    // Dealing for a table splits one draw, so nobody shares a mission
+    let per_player = if names.is_empty() { count } else { count.min(missions.missions.len() / names.len()) };
+    let notice = if missions.missions.is_empty() {
+        Some("No missions match these filters".to_string())
+    } else if per_player == 0 {
+        Some(format!("Not enough missions match to deal one to each of {} players", names.len()))
+    } else if per_player < count {
+        Some(format!("Only {} missions each, there aren't enough for {} per player", per_player, count))
+    } else {
+        None
+    };
+    let chosen: Vec<_> = model::draw_missions(&missions.missions, per_player * names.len().max(1), &mut rng)
+        .into_iter()
         .cloned()
         .collect();
-    
-    let missions_data: Vec<MissionDisplayData> = chosen.into_iter()
-        .map(|m| resolve_mission_display(m, &mut rng, &state))
+
+    let catalog = state.catalog();
+    let ticked = state.seed_checklists(&seed);
+    let mut resolved: Vec<ResolvedMission> = chosen.into_iter()
+        .map(|m| {
+            let mut resolved = model::resolve(m, &mut rng, &missions, &catalog);
+            resolved.attach_checklists(&checklist::url(&seed), &ticked);
+            resolved
+        })
         .collect();
 
+    let link = |seat: Option<usize>| {
+        let mut link = format!(
+            "/{}/solo?count={}&players={}",
+            seed,
+            count,
+            percent_encoding::utf8_percent_encode(&players, percent_encoding::NON_ALPHANUMERIC),
+        );
+        if let Some(seat) = seat {
+            link.push_str(&format!("&seat={}", seat));
+        }
+        if !filter.is_empty() {
+            link.push('&');
+            link.push_str(&filter.query());
+        }
+        // Even an empty ban list goes in, so whoever opens the link can't
+        // change the deal with bans of their own
+        if filter.banned.is_empty() {
+            link.push_str("&ban=");
+        }
+        link
+    };
+    let mut hands = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let hand = resolved.drain(..per_player).collect();
+        hands.push(Hand { name: name.clone(), link: link(Some(i + 1)), missions: hand });
+    }
+    let seat = query.seat.filter(|&seat| seat >= 1 && seat <= hands.len());
+    if let Some(seat) = seat {
+        hands = vec![hands.swap_remove(seat - 1)];
+    }
+
     let template = SoloTemplate {
-        missions: missions_data,
+        missions: resolved,
+        hands,
+        form: Some(SoloForm {
+            action: format!("/{}/solo", seed),
+            count,
+            players: players.clone(),
+            filter: filter.clone(),
+            banned: filter.banned.len(),
+            table_link: seat.map(|_| link(None)),
+            notice,
+        }),
         view_name: "solo".to_string(),
     };
     render_response(headers, template.render().unwrap(), Some(seed))
@@ -280,32 +376,14 @@ struct RandomizerTemplate {
 }
 
 async fn randomizer(
+    State(state): State<AppState>,
     axum::extract::Path(seed): axum::extract::Path<String>,
     headers: HeaderMap,
 ) -> Html<String> {
-    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-    std::hash::Hash::hash(&seed, &mut hasher);
-    let seed_u64 = std::hash::Hasher::finish(&hasher);
-    let mut rng = StdRng::seed_from_u64(seed_u64);
-    
-    let c = model::CHARACTERS.choose(&mut rng).unwrap();
-    let k = model::KARTS.choose(&mut rng).unwrap();
-    let w = model::WHEELS.choose(&mut rng).unwrap();
-    let g = model::GLIDERS.choose(&mut rng).unwrap();
-    
-    let loadout = Loadout {
-        char_img: format!("/assets/characters/{}.webp", c.to_lowercase().replace(" ", "_")),
-        char_name: c.to_string(),
-        kart_img: format!("/assets/karts/{}.webp", k.to_lowercase().replace(" ", "_")),
-        kart_name: k.to_string(),
-        wheel_img: format!("/assets/wheels/{}.webp", w.to_lowercase().replace(" ", "_")),
-        wheel_name: w.to_string(),
-        glider_img: format!("/assets/gliders/{}.webp", g.to_lowercase().replace(" ", "_")),
-        glider_name: g.to_string(),
-    };
+    let mut rng = seed::rng(&seed);
 
     let template = RandomizerTemplate {
-        loadout,
+        loadout: model::random_loadout(&mut rng, &state.catalog()),
         view_name: "randomizer".to_string(),
     };
     render_response(headers, template.render().unwrap(), Some(seed))
@@ -321,8 +399,9 @@ async fn all_missions(
     State(state): State<AppState>,
     headers: HeaderMap,
 ) -> Html<String> {
+    let missions = state.missions();
     let template = AllMissionsTemplate {
-        missions: &state.missions,
+        missions: &missions,
     };
     render_response(headers, template.render().unwrap(), None)
 }
@@ -335,19 +414,19 @@ async fn mission_view(
     // Check main.rs imports for percent_encoding, if not present we might need to rely on direct match or add it
     // Assuming simple name match for now or re-adding helper function
     
-    let mission = find_mission(&state.missions, &name);
+    let mission = find_mission(&state.missions(), &name);
 
     if let Some(mission) = mission {
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        std::hash::Hash::hash(&seed, &mut hasher);
-        let seed_u64 = std::hash::Hasher::finish(&hasher);
-        let mut rng = StdRng::seed_from_u64(seed_u64);
+        let mut rng = seed::rng(&seed);
 
-        let mission_display = resolve_mission_display(mission, &mut rng, &state);
+        let mut resolved = model::resolve(mission, &mut rng, &state.missions(), &state.catalog());
+        resolved.attach_checklists(&checklist::url(&seed), &state.seed_checklists(&seed));
 
         let template = SoloTemplate {
-            missions: vec![mission_display],
-            view_name: format!("mission/{}", percent_encoding::utf8_percent_encode(&name, percent_encoding::NON_ALPHANUMERIC).to_string()),
+            missions: vec![resolved],
+            hands: Vec::new(),
+            form: None,
+            view_name: format!("mission/{}", percent_encoding::utf8_percent_encode(&name, percent_encoding::NON_ALPHANUMERIC)),
         };
         render_response(headers, template.render().unwrap(), Some(seed))
 
@@ -356,6 +435,40 @@ async fn mission_view(
     }
 }
 
+// Manual trigger for the same reload the file watcher does
+// Needs the configured admin token. Problems with the new files are only logged,
+// the response never says more than whether the reload happened.
+async fn admin_reload(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let Some(token) = &state.admin_token else {
+        return (StatusCode::NOT_FOUND, "Not found");
+    };
+    let given = headers.get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !same_secret(given, token) {
+        tracing::warn!("Missions reload refused: bad or missing admin token");
+        return (StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+    match state.reload_missions() {
+        Ok(()) => (StatusCode::OK, "Missions reloaded"),
+        Err(e) => {
+            tracing::error!("Missions reload rejected: {}", e);
+            (StatusCode::UNPROCESSABLE_ENTITY, "Reload rejected, see the server log")
+        }
+    }
+}
+
+// Compares every byte so the time taken doesn't give away how much of a guess was right
+fn same_secret(given: &str, secret: &str) -> bool {
+    given.len() == secret.len()
+        && given.bytes().zip(secret.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    ([(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4")], state.metrics())
+}
+
 fn find_mission(data: &MissionsData, name: &str) -> Option<Mission> {
     // Simple lookup if encoding crate missing, otherwise use decode
     // We didn't re-add percent_encoding to imports in my recent 'Revert' step, 
@@ -371,325 +484,3 @@ fn find_mission(data: &MissionsData, name: &str) -> Option<Mission> {
         .find(|m| m.name.eq_ignore_ascii_case(&name_decoded))
         .cloned()
 }
-
-// --- Traitor Mode Handlers & Structs ---
-
-#[derive(Template)]
-#[template(path = "traitor_setup.html")]
-struct TraitorSetupTemplate;
-
-async fn traitor_setup(headers: HeaderMap) -> Html<String> {
-    let template = TraitorSetupTemplate;
-    render_response(headers, template.render().unwrap(), None)
-}
-
-async fn traitor_create(State(state): State<AppState>) -> Redirect {
-    // Use a short random string for room ID
-    let room_id: String = rand::thread_rng()
-        .sample_iter(&rand::distributions::Alphanumeric)
-        .take(4)
-        .map(char::from)
-        .collect::<String>()
-        .to_uppercase();
-
-    let (tx, _rx) = broadcast::channel(100);
-
-    let lobby = Lobby {
-        players: Vec::new(),
-        status: LobbyStatus::Waiting,
-        seed: rand::thread_rng().gen::<u32>().to_string(),
-        tx,
-    };
-
-    state.lobbies.write().unwrap().insert(room_id.clone(), lobby);
-    
-    Redirect::to(&format!("/traitor/{}", room_id))
-}
-
-#[derive(Deserialize)]
-struct JoinForm {
-    name: String,
-}
-
-async fn traitor_join_view(
-    axum::extract::Path(room_id): axum::extract::Path<String>,
-) ->  impl IntoResponse {
-    Redirect::to(&format!("/traitor/{}", room_id))
-}
-
-async fn traitor_join_action(
-    State(state): State<AppState>,
-    axum::extract::Path(room_id): axum::extract::Path<String>,
-    Form(form): Form<JoinForm>,
-) -> Response {
-    println!("Join action triggered for room {}, name {}", room_id, form.name);
-    // 1. Lock lobbies
-    // 2. Add player if not started
-    let mut lobbies = state.lobbies.write().unwrap();
-    
-    if let Some(lobby) = lobbies.get_mut(&room_id) {
-        if lobby.status != LobbyStatus::Waiting {
-             return (StatusCode::BAD_REQUEST, "Game already started").into_response();
-        }
-        
-        // Check duplicate name? For now, allow duplicates or suffix them. 
-        // Better to avoid confusion: simply allow.
-        
-        lobby.players.push(Player {
-            name: form.name.clone(),
-            is_traitor: false,
-        });
-        
-        // Redirect to lobby + set cookie ideally, but for now we'll just redirect with query param or just simple flow
-        // The user says "link to share... sign in... presses go".
-        // How do we know WHICH player this browser is? 
-        // We need a cookie or local storage. 
-        // Simplest: Redirect to lobby with ?player=NAME. Weak security but fine for casual app.
-        
-        // Encode name for URL
-        let encoded_name = percent_encoding::utf8_percent_encode(&form.name, percent_encoding::NON_ALPHANUMERIC).to_string();
-        // Broadcast update
-        let _ = lobby.tx.send(LobbyEvent::PlayerJoined(form.name.clone()));
-
-        return Redirect::to(&format!("/traitor/{}?player={}", room_id, encoded_name)).into_response();
-    }
-
-    (StatusCode::NOT_FOUND, "Lobby not found").into_response()
-}
-
-#[derive(Template)]
-#[template(path = "traitor_lobby.html")]
-struct TraitorLobbyTemplate {
-    room_id: String,
-    player_name: String,
-    players: Vec<Player>,
-}
-
-#[derive(Deserialize)]
-struct LobbyQuery {
-    player: Option<String>,
-}
-
-async fn traitor_lobby_view(
-    State(state): State<AppState>,
-    axum::extract::Path(room_id): axum::extract::Path<String>,
-    Query(params): Query<LobbyQuery>,
-    headers: HeaderMap,
-) -> impl IntoResponse {
-    let mut lobbies = state.lobbies.write().unwrap();
-    if let Some(lobby) = lobbies.get_mut(&room_id) {
-        // If player param is missing, we treat them as "Joining"
-        // We can reuse the lobby template but conditionally render the form
-        
-        let player_name = params.player.clone().unwrap_or_default();
-        
-        if !player_name.is_empty() {
-             if lobby.status == LobbyStatus::Started {
-                 let encoded_name = percent_encoding::utf8_percent_encode(&player_name, percent_encoding::NON_ALPHANUMERIC).to_string();
-                 return Redirect::to(&format!("/traitor/{}/role?player={}", room_id, encoded_name)).into_response();
-             }
-
-             // Re-add player if missing (Reconnect logic)
-             if !lobby.players.iter().any(|p| p.name == player_name) {
-                 lobby.players.push(Player {
-                     name: player_name.clone(),
-                     is_traitor: false,
-                 });
-                 let _ = lobby.tx.send(LobbyEvent::PlayerJoined(player_name.clone()));
-             }
-        }
-        
-        let template = TraitorLobbyTemplate {
-            room_id,
-            player_name,
-            players: lobby.players.clone(),
-        };
-        // We might want to pass view_name: "traitor" here if we want the pill selector to work in the lobby
-        // but for now, the user mostly cares about the swipe navigation between the main game modes.
-        return render_response(headers, template.render().unwrap(), None).into_response();
-    }
-    (StatusCode::NOT_FOUND, "Lobby not found").into_response()
-}
-
-struct PlayerLeaveGuard {
-    state: AppState,
-    room_id: String,
-    player_name: String,
-}
-
-impl Drop for PlayerLeaveGuard {
-    fn drop(&mut self) {
-        let state = self.state.clone();
-        let room_id = self.room_id.clone();
-        let name = self.player_name.clone();
-        println!("Player {} disconnected (Drop)", name);
-        
-        // Spawn async task for cleanup since Drop is sync
-        tokio::spawn(async move {
-            let mut lobbies = state.lobbies.write().unwrap();
-            if let Some(lobby) = lobbies.get_mut(&room_id) {
-                // Remove player from list
-                if let Some(pos) = lobby.players.iter().position(|p| p.name == name) {
-                    lobby.players.remove(pos);
-                    // Broadcast leave event
-                    let _ = lobby.tx.send(LobbyEvent::PlayerLeft(name));
-                    println!("Player left event broadcasted");
-                }
-            }
-        });
-    }
-}
-
-// SSE Endpoint
-async fn traitor_lobby_sse(
-    State(state): State<AppState>,
-    axum::extract::Path(room_id): axum::extract::Path<String>,
-    Query(params): Query<LobbyQuery>,
-) -> impl IntoResponse {
-    let lobbies = state.lobbies.read().unwrap();
-    
-    // We expect a player name for the connection to track presence
-    let player_name = params.player.unwrap_or_default();
-    println!("SSE request for room {} with player '{}'", room_id, player_name);
-
-    if let Some(lobby) = lobbies.get(&room_id) {
-        let rx = lobby.tx.subscribe();
-        let stream = BroadcastStream::new(rx);
-        
-        // Guard to handle disconnect
-        let guard = if !player_name.is_empty() {
-            println!("Player {} connected to SSE", player_name);
-            Some(Arc::new(PlayerLeaveGuard {
-                state: state.clone(),
-                room_id: room_id.clone(),
-                player_name,
-            }))
-        } else {
-            None
-        };
-
-        // Capture guard in closure
-        let stream = stream.map(move |msg| {
-             let _keep_alive = guard.as_ref();
-             match msg {
-                 Ok(LobbyEvent::PlayerJoined(name)) => {
-                     let html = format!("<li id='player-{}' class='bg-gray-50 p-3 rounded shadow-sm border flex items-center'><span class='font-medium'>{}</span></li>", name, name);
-                     Ok::<Event, Infallible>(Event::default().event("player_joined").data(html))
-                 },
-                 Ok(LobbyEvent::PlayerLeft(name)) => {
-                     let script = format!("<div id='player-{}' hx-swap-oob='delete'></div>", name);
-                     Ok::<Event, Infallible>(Event::default().event("player_left").data(script))
-                 },
-                 Ok(LobbyEvent::GameStarted) => {
-                     Ok::<Event, Infallible>(Event::default().event("game_start").data("started"))
-                 },
-                 Err(_) => Ok::<Event, Infallible>(Event::default()) // Ignore lag errors
-             }
-        });
-
-        Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::default()).into_response()
-    } else {
-        let (_, rx) = broadcast::channel::<LobbyEvent>(1);
-        let stream = BroadcastStream::new(rx).map(|_| Ok::<Event, Infallible>(Event::default()));
-        Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::default()).into_response()
-    }
-}
-
-async fn traitor_start(
-    State(state): State<AppState>,
-    axum::extract::Path(room_id): axum::extract::Path<String>,
-) -> impl IntoResponse {
-    let mut lobbies = state.lobbies.write().unwrap();
-    if let Some(lobby) = lobbies.get_mut(&room_id) {
-        if lobby.players.len() < 1 { // Allow 1 for testing, though traitor implies >1
-             return (StatusCode::BAD_REQUEST, "Not enough players").into_response();
-        }
-        if lobby.status == LobbyStatus::Started {
-            return (StatusCode::OK, "Already started").into_response();
-        }
-
-        // Assign Traitor
-        let traitor_idx = rand::thread_rng().gen_range(0..lobby.players.len());
-        lobby.players[traitor_idx].is_traitor = true;
-        lobby.status = LobbyStatus::Started;
-        
-        let _ = lobby.tx.send(LobbyEvent::GameStarted);
-        
-        // Return 200 OK
-        return StatusCode::OK.into_response();
-    }
-    (StatusCode::NOT_FOUND, "Lobby not found").into_response()
-}
-
-#[derive(Deserialize)]
-struct NameChangeForm {
-    name: String,
-}
-
-async fn traitor_change_name_action(
-    State(state): State<AppState>,
-    axum::extract::Path((room_id, old_name)): axum::extract::Path<(String, String)>,
-    Form(form): Form<NameChangeForm>,
-) -> impl IntoResponse {
-    println!("Change name action: {} -> {}", old_name, form.name);
-    let mut lobbies = state.lobbies.write().unwrap();
-    if let Some(lobby) = lobbies.get_mut(&room_id) {
-        
-        // Find player
-        if let Some(pos) = lobby.players.iter().position(|p| p.name == old_name) {
-            let new_name = form.name.trim().to_string();
-            if !new_name.is_empty() && new_name != old_name {
-                lobby.players[pos].name = new_name.clone();
-                // Send Leave + Join to emulate replacement
-                let _ = lobby.tx.send(LobbyEvent::PlayerLeft(old_name));
-                let _ = lobby.tx.send(LobbyEvent::PlayerJoined(new_name.clone()));
-                
-                // We need to redirect the user to update their URL params
-                let encoded_name = percent_encoding::utf8_percent_encode(&new_name, percent_encoding::NON_ALPHANUMERIC).to_string();
-                return Redirect::to(&format!("/traitor/{}?player={}", room_id, encoded_name)).into_response();
-            }
-        }
-    }
-    StatusCode::OK.into_response()
-}
-
-#[derive(Template)]
-#[template(path = "traitor_role.html")]
-struct TraitorRoleTemplate {
-    is_traitor: bool,
-    player_name: String,
-    mission: Mission,
-}
-
-async fn traitor_role_view(
-    State(state): State<AppState>,
-    axum::extract::Path(room_id): axum::extract::Path<String>,
-    Query(params): Query<LobbyQuery>,
-    headers: HeaderMap,
-) -> impl IntoResponse {
-    let lobbies = state.lobbies.read().unwrap();
-    if let Some(lobby) = lobbies.get(&room_id) {
-        // Find player
-        // Note: players have names. We trust query param.
-        if let Some(player) = lobby.players.iter().find(|p| Some(&p.name) == params.player.as_ref()) {
-            
-            // Get mission using lobby seed
-             let mut hasher = std::collections::hash_map::DefaultHasher::new();
-            std::hash::Hash::hash(&lobby.seed, &mut hasher);
-            let seed_u64 = std::hash::Hasher::finish(&hasher);
-            let mut rng = StdRng::seed_from_u64(seed_u64);
-            
-            // Traitor mode uses coop missions "for now we can just use the coop missions"
-            let mission = state.missions.coop_granprix.choose(&mut rng).unwrap().clone();
-            
-            let template = TraitorRoleTemplate {
-                is_traitor: player.is_traitor,
-                player_name: player.name.clone(),
-                mission,
-            };
-            return render_response(headers, template.render().unwrap(), Some(lobby.seed.clone())).into_response();
-        }
-        return (StatusCode::FORBIDDEN, "Player not in lobby").into_response();
-    }
-    (StatusCode::NOT_FOUND, "Lobby not found").into_response()
-}
@@ -1,23 +1,199 @@
+use std::collections::{BTreeSet, HashMap};
+use rand::seq::SliceRandom;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RawMission")]
 pub struct Mission {
     pub name: String,
     pub description: String,
-    #[serde(default)]
     pub details: Vec<String>,
+    pub requirements: Vec<Requirement>,
+    /// Free-form labels such as "online" or "offline", matched by filters
+    pub tags: Vec<String>,
+    pub difficulty: Option<Difficulty>,
+    /// Table sizes the mission makes sense for
+    pub player_count: Option<PlayerCount>,
+    /// Relative chance of being drawn compared to the rest of its pool
+    pub weight: u32,
+}
+
+/// How rough a mission is on whoever draws it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    /// Same spelling as in missions.json and filter URLs
+    pub fn slug(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn from_slug(slug: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.slug() == slug)
+    }
+}
+
+/// Smallest and largest table a mission works for, either end open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerCount {
     #[serde(default)]
-    pub needs_random_loadout: bool,
+    pub min: Option<usize>,
     #[serde(default)]
-    pub needs_random_item: bool,
+    pub max: Option<usize>,
+}
+
+impl PlayerCount {
+    pub fn fits(&self, players: usize) -> bool {
+        self.min.is_none_or(|min| players >= min) && self.max.is_none_or(|max| players <= max)
+    }
+}
+
+/// Something random (or listed) that has to be shown alongside a mission.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Requirement {
+    /// `count` distinct entries from a catalog
+    Pick {
+        from: Category,
+        #[serde(default = "one")]
+        count: usize,
+    },
+    /// A number in `min..=max`
+    Number { min: u32, max: u32 },
+    /// Character, kart, wheels and glider
+    Loadout,
+    /// `count` distinct seats out of a table of `of` players
+    Players {
+        count: usize,
+        #[serde(default = "default_table_size")]
+        of: usize,
+    },
+    /// Another mission drawn from one of the pools, resolved in turn
+    Mission { pool: MissionPool },
+    /// Every entry of a catalog, to tick off during the race.
+    /// With a `goal`, that many ticks complete it instead of all of them.
+    Checklist {
+        from: Category,
+        #[serde(default)]
+        goal: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Items,
+    GachaItems,
+    Characters,
+    Karts,
+    Wheels,
+    Gliders,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissionPool {
+    Missions,
+    CoopGranprix,
+    CoopSingle,
+    TraitorObjectives,
+}
+
+impl MissionPool {
+    pub fn label(self) -> &'static str {
+        match self {
+            MissionPool::Missions => "Mission",
+            MissionPool::CoopGranprix => "Grand Prix",
+            MissionPool::CoopSingle => "Single Race",
+            MissionPool::TraitorObjectives => "Secret Objective",
+        }
+    }
+}
+
+fn one() -> usize { 1 }
+fn default_weight() -> u32 { DEFAULT_WEIGHT }
+
+pub const DEFAULT_WEIGHT: u32 = 1;
+fn default_table_size() -> usize { 4 }
+
+// On-disk shape. The `needs_*` flags predate `requirements` and are still accepted;
+// they are turned into requirements in the order the resolver used to roll them.
+#[derive(Deserialize)]
+struct RawMission {
+    name: String,
+    description: String,
+    #[serde(default)]
+    details: Vec<String>,
+    #[serde(default)]
+    requirements: Vec<Requirement>,
+    #[serde(default)]
+    needs_random_loadout: bool,
+    #[serde(default)]
+    needs_random_item: bool,
     #[serde(default)]
-    pub needs_random_number: Option<u32>,
+    needs_random_number: Option<u32>,
     #[serde(default)]
-    pub needs_coop_singles: bool,
+    needs_coop_singles: bool,
     #[serde(default)]
-    pub all_items: bool,
+    all_items: bool,
     #[serde(default)]
-    pub needs_gacha_item_checklist: bool,
+    needs_gacha_item_checklist: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    difficulty: Option<Difficulty>,
+    #[serde(default)]
+    player_count: Option<PlayerCount>,
+    #[serde(default = "default_weight")]
+    weight: u32,
+}
+
+impl From<RawMission> for Mission {
+    fn from(raw: RawMission) -> Self {
+        let mut requirements = Vec::new();
+        if raw.needs_random_item {
+            requirements.push(Requirement::Pick { from: Category::Items, count: 1 });
+        }
+        if raw.needs_random_loadout {
+            requirements.push(Requirement::Loadout);
+        }
+        if let Some(max) = raw.needs_random_number {
+            requirements.push(Requirement::Number { min: 1, max });
+        }
+        if raw.needs_coop_singles {
+            requirements.push(Requirement::Mission { pool: MissionPool::CoopSingle });
+        }
+        if raw.all_items {
+            requirements.push(Requirement::Checklist { from: Category::Items, goal: None });
+        }
+        if raw.needs_gacha_item_checklist {
+            requirements.push(Requirement::Checklist { from: Category::GachaItems, goal: None });
+        }
+        requirements.extend(raw.requirements);
+
+        Self {
+            name: raw.name,
+            description: raw.description,
+            details: raw.details,
+            requirements,
+            tags: raw.tags,
+            difficulty: raw.difficulty,
+            player_count: raw.player_count,
+            weight: raw.weight,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,48 +201,291 @@ pub struct MissionsData {
     pub missions: Vec<Mission>,
     pub coop_granprix: Vec<Mission>,
     pub coop_single: Vec<Mission>,
+    /// Secret objectives dealt to traitors only. Optional in missions.json.
+    #[serde(default)]
+    pub traitor_objectives: Vec<Mission>,
+}
+
+impl MissionsData {
+    pub fn pool(&self, pool: MissionPool) -> &[Mission] {
+        match pool {
+            MissionPool::Missions => &self.missions,
+            MissionPool::CoopGranprix => &self.coop_granprix,
+            MissionPool::CoopSingle => &self.coop_single,
+            MissionPool::TraitorObjectives => &self.traitor_objectives,
+        }
+    }
+}
+
+/// Characters, vehicle parts and items, loaded from catalog.json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Catalog {
+    pub characters: Vec<Part>,
+    pub karts: Vec<Part>,
+    pub wheels: Vec<Part>,
+    pub gliders: Vec<Part>,
+    pub items: Vec<Part>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Part {
+    pub name: String,
+    pub slug: String,
+    /// Relative to the assets directory
+    pub image: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Part {
+    pub fn img_url(&self) -> String {
+        format!("/assets/{}", self.image)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+impl Catalog {
+    // Order matters: seeded picks index into these lists
+    pub fn entries(&self, category: Category) -> Vec<&Part> {
+        match category {
+            Category::Items => self.items.iter().collect(),
+            Category::GachaItems => self.items.iter().filter(|p| p.has_tag("gacha")).collect(),
+            Category::Characters => self.characters.iter().collect(),
+            Category::Karts => self.karts.iter().collect(),
+            Category::Wheels => self.wheels.iter().collect(),
+            Category::Gliders => self.gliders.iter().collect(),
+        }
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        let lists = [
+            ("characters", &self.characters),
+            ("karts", &self.karts),
+            ("wheels", &self.wheels),
+            ("gliders", &self.gliders),
+            ("items", &self.items),
+        ];
+        for (label, parts) in lists {
+            if parts.is_empty() {
+                return Err(format!("catalog: {} must not be empty", label));
+            }
+            let mut slugs = std::collections::HashSet::new();
+            for part in parts {
+                if !slugs.insert(part.slug.as_str()) {
+                    return Err(format!("catalog: duplicate {} slug '{}'", label, part.slug));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Category {
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::Items | Category::GachaItems => "Items",
+            Category::Characters => "Characters",
+            Category::Karts => "Karts",
+            Category::Wheels => "Wheels",
+            Category::Gliders => "Gliders",
+        }
+    }
+
+    /// Same spelling as in missions.json, used in checklist URLs
+    pub fn slug(self) -> &'static str {
+        match self {
+            Category::Items => "items",
+            Category::GachaItems => "gacha_items",
+            Category::Characters => "characters",
+            Category::Karts => "karts",
+            Category::Wheels => "wheels",
+            Category::Gliders => "gliders",
+        }
+    }
 }
 
-// Constants for Random Generation
-pub const ITEMS: &[&str] = &[
-    "coin", "banana", "triple_banana", "green_shell", "triple_green_shells",
-    "red_shell", "triple_red_shells", "mushroom", "triple_mushrooms", "golden_mushroom",
-    "super_star", "lightning", "bob-omb", "boo", "fire_flower", "boomerang_flower",
-    "piranha_plant", "bullet_bill", "spiny_shell", "super_horn", "blooper", "crazy_eight",
-];
-
-pub const GACHA_ITEMS: &[&str] = &[
-    "bob-omb", "super_horn", "boomerang_flower", "fire_flower", "piranha_plant", "boo", "crazy_eight",
-];
-
-pub const KARTS: &[&str] = &[
-    "Standard Kart", "Pipe Frame", "B Dasher", "Mach 8", "Steel Driver", "Cat Cruiser",
-    "Circuit Special", "Tri-Speeder", "Badwagon", "Prancer", "Biddybuggy", "Landship",
-    "Sneeker", "Sports Coupe", "GLA", "W 25 Silver Arrow", "300 SL Roadster", "Blue Falcon",
-    "Tanooki Kart", "Bone Rattler", "Inkstriker", "Master Cycle", "Streetle", "P-Wing",
-    "Koopa Clown", "Standard Bike", "Comet", "Sport Bike", "The Duke", "Flame Rider",
-    "Varmint", "Mr. Scooty", "Jet Bike", "Yoshi Bike", "Master Cycle Zero", "City Tripper",
-];
-
-pub const WHEELS: &[&str] = &[
-    "Standard", "Monster", "Roller", "Slim", "Slick", "Metal", "Button", "Off-Road",
-    "Sponge", "Wood", "Cushion", "Blue Standard", "Hot Monster", "Azure Roller",
-    "Crimson Slim", "Cyber Slick", "Retro Off-Road", "GLA Tires", "Triforce Tires",
-    "Leaf Tires", "Ancient Tires",
-];
-
-pub const GLIDERS: &[&str] = &[
-    "Super Glider", "Cloud Glider", "Wario Wing", "Waddle Wing", "Peach Parasol",
-    "Parachute", "Parafoil", "Flower Glider", "Bowser Kite", "Plane Glider",
-    "MKTV Parafoil", "Hylian Kite", "Paper Glider", "Paraglider",
-];
-
-pub const CHARACTERS: &[&str] = &[
-    "Baby Daisy", "Baby Luigi", "Baby Mario", "Baby Peach", "Baby Rosalina", "Birdo",
-    "Cat Peach", "Dry Bones", "Lemmy", "Bowser Jr.", "Daisy", "Diddy Kong", "Iggy",
-    "Inkling Boy", "Inkling Girl", "Isabelle", "Kamek", "Koopa Troopa", "Lakitu",
-    "Larry", "Link", "Luigi", "Ludwig", "Mario", "Morton", "Pauline", "Peach",
-    "Peachette", "Rosalina", "Roy", "Shy Guy", "Toad", "Toadette", "Villager",
-    "Wendy", "Wiggler", "Yoshi", "Bowser", "Donkey Kong", "Dry Bowser", "Funky Kong",
-    "King Boo", "Metal Mario", "Petey Piranha", "Pink Gold Peach", "Wario", "Waluigi",
-];
+#[derive(Debug, Clone)]
+pub struct Loadout {
+    pub character: Part,
+    pub kart: Part,
+    pub wheel: Part,
+    pub glider: Part,
+}
+
+/// A mission with all of its random parts rolled.
+#[derive(Debug, Clone)]
+pub struct ResolvedMission {
+    pub mission: Mission,
+    pub requirements: Vec<Resolved>,
+}
+
+/// One rolled `Requirement`, in the same order as on the mission.
+#[derive(Debug, Clone)]
+pub enum Resolved {
+    Pick(Category, Vec<Part>),
+    Number(u32),
+    Loadout(Box<Loadout>),
+    Players(Vec<usize>),
+    Mission(MissionPool, Box<ResolvedMission>),
+    Checklist(Checklist),
+}
+
+/// Slugs ticked off per checklist category. One of these is shared by everyone
+/// on the same seed, or by everyone in a traitor lobby.
+pub type ChecklistState = HashMap<Category, BTreeSet<String>>;
+
+/// Every entry of a catalog and which of them have been ticked off.
+#[derive(Debug, Clone)]
+pub struct Checklist {
+    pub category: Category,
+    pub parts: Vec<Part>,
+    pub done: BTreeSet<String>,
+    /// Ticks needed to complete it, all of them if `None`
+    pub goal: Option<usize>,
+    /// Where ticks get posted, `/{url}/{category}/{slug}`. Empty renders a read-only list.
+    pub url: String,
+}
+
+impl Checklist {
+    pub fn new(category: Category, goal: Option<usize>, catalog: &Catalog) -> Self {
+        Self {
+            category,
+            parts: catalog.entries(category).into_iter().cloned().collect(),
+            done: BTreeSet::new(),
+            goal,
+            url: String::new(),
+        }
+    }
+
+    pub fn contains(&self, slug: &str) -> bool {
+        self.parts.iter().any(|p| p.slug == slug)
+    }
+
+    pub fn is_done(&self, part: &Part) -> bool {
+        self.done.contains(&part.slug)
+    }
+
+    /// Ticks that still name an entry, so renamed or removed items don't count
+    pub fn done_count(&self) -> usize {
+        self.parts.iter().filter(|p| self.is_done(p)).count()
+    }
+
+    pub fn target(&self) -> usize {
+        self.goal.unwrap_or(self.parts.len())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.done_count() >= self.target()
+    }
+
+    /// How far along towards the target, capped at 100
+    pub fn percent(&self) -> usize {
+        (self.done_count() * 100).checked_div(self.target()).unwrap_or(100).min(100)
+    }
+}
+
+impl ResolvedMission {
+    /// Makes every checklist in the mission, nested ones included, tickable
+    /// through `url` and fills in what has been ticked so far.
+    pub fn attach_checklists(&mut self, url: &str, state: &ChecklistState) {
+        for req in &mut self.requirements {
+            match req {
+                Resolved::Checklist(checklist) => {
+                    checklist.url = url.to_string();
+                    checklist.done = state.get(&checklist.category).cloned().unwrap_or_default();
+                }
+                Resolved::Mission(_, nested) => nested.attach_checklists(url, state),
+                _ => {}
+            }
+        }
+    }
+}
+
+// Guards against missions that (indirectly) draw from their own pool
+const MAX_NESTING: usize = 3;
+
+pub fn resolve<R: Rng>(mission: Mission, rng: &mut R, data: &MissionsData, catalog: &Catalog) -> ResolvedMission {
+    resolve_nested(mission, rng, data, catalog, 0)
+}
+
+// Requirements are rolled strictly in list order, which is what keeps seeded links
+// reproducible. Changing how an existing variant consumes the RNG changes old links.
+fn resolve_nested<R: Rng>(
+    mission: Mission,
+    rng: &mut R,
+    data: &MissionsData,
+    catalog: &Catalog,
+    depth: usize,
+) -> ResolvedMission {
+    let mut requirements = Vec::new();
+    for req in &mission.requirements {
+        let resolved = match *req {
+            Requirement::Pick { from, count } => {
+                let entries = catalog.entries(from);
+                // `choose` for a single pick keeps the draw identical to the old needs_random_item
+                let picked: Vec<&&Part> = if count == 1 {
+                    entries.choose(rng).into_iter().collect()
+                } else {
+                    entries.choose_multiple(rng, count).collect()
+                };
+                Resolved::Pick(from, picked.into_iter().map(|p| (*p).clone()).collect())
+            }
+            Requirement::Number { min, max } => Resolved::Number(rng.gen_range(min..=max)),
+            Requirement::Loadout => Resolved::Loadout(Box::new(random_loadout(rng, catalog))),
+            Requirement::Players { count, of } => {
+                let mut seats = rand::seq::index::sample(rng, of, count).into_vec();
+                seats.sort();
+                Resolved::Players(seats.into_iter().map(|s| s + 1).collect())
+            }
+            Requirement::Mission { pool } => {
+                if depth >= MAX_NESTING {
+                    continue;
+                }
+                match draw_mission(data.pool(pool), rng).cloned() {
+                    Some(sub) => Resolved::Mission(pool, Box::new(resolve_nested(sub, rng, data, catalog, depth + 1))),
+                    None => continue,
+                }
+            }
+            Requirement::Checklist { from, goal } => Resolved::Checklist(Checklist::new(from, goal, catalog)),
+        };
+        requirements.push(resolved);
+    }
+
+    ResolvedMission { mission, requirements }
+}
+
+// Weighted draws use the RNG differently, so pools nobody has weighted keep drawing
+// exactly like they did before weights existed and old links stay valid.
+fn unweighted(pool: &[Mission]) -> bool {
+    pool.iter().all(|m| m.weight == DEFAULT_WEIGHT)
+}
+
+/// One mission from `pool`, heavier ones more likely.
+pub fn draw_mission<'a, R: Rng>(pool: &'a [Mission], rng: &mut R) -> Option<&'a Mission> {
+    if unweighted(pool) {
+        return pool.choose(rng);
+    }
+    pool.choose_weighted(rng, |m| m.weight).ok()
+}
+
+/// Up to `amount` distinct missions from `pool`, heavier ones more likely.
+pub fn draw_missions<'a, R: Rng>(pool: &'a [Mission], amount: usize, rng: &mut R) -> Vec<&'a Mission> {
+    if unweighted(pool) {
+        return pool.choose_multiple(rng, amount).collect();
+    }
+    pool.choose_multiple_weighted(rng, amount, |m| f64::from(m.weight))
+        .map(|drawn| drawn.collect())
+        .unwrap_or_default()
+}
+
+pub fn random_loadout<R: Rng>(rng: &mut R, catalog: &Catalog) -> Loadout {
+    Loadout {
+        character: catalog.characters.choose(rng).unwrap().clone(),
+        kart: catalog.karts.choose(rng).unwrap().clone(),
+        wheel: catalog.wheels.choose(rng).unwrap().clone(),
+        glider: catalog.gliders.choose(rng).unwrap().clone(),
+    }
+}
@@ -0,0 +1,166 @@
+//! Seed string -> RNG derivation.
+//!
+//! Seeds end up in shared links (`/{seed}/solo`), so the same seed has to produce the same
+//! missions forever. A seed may carry a version prefix that picks the hash:
+//!
+//! - `v1:<text>`: 64-bit FNV-1a over the UTF-8 bytes of `<text>`.
+//! - anything else is a legacy seed: SipHash-1-3 with zero keys over the bytes followed by
+//!   `0xff`. That is what `DefaultHasher::new()` produced for a `String` when links were
+//!   first shared, pinned here so a toolchain bump can't change it.
+//!
+//! Either hash seeds a `ChaCha12Rng` via `seed_from_u64`. That's what `StdRng` was in
+//! rand 0.8, but `StdRng` is allowed to change between rand releases, so it's named directly.
+//!
+//! Never change what an existing version does. Add a new prefix instead.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use siphasher::sip::SipHasher13;
+use std::hash::Hasher;
+
+pub type SeedRng = ChaCha12Rng;
+
+/// Prefix put on freshly generated seeds.
+pub const CURRENT_VERSION: &str = "v1";
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+pub fn rng(seed: &str) -> SeedRng {
+    SeedRng::seed_from_u64(hash(seed))
+}
+
+pub fn hash(seed: &str) -> u64 {
+    match seed.strip_prefix("v1:") {
+        Some(rest) => fnv1a(rest.as_bytes()),
+        None => legacy(seed),
+    }
+}
+
+/// A new random seed in the current version, e.g. `v1:3141592653`.
+pub fn generate() -> String {
+    format!("{}:{}", CURRENT_VERSION, rand::thread_rng().gen::<u32>())
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &b| (hash ^ b as u64).wrapping_mul(FNV_PRIME))
+}
+
+fn legacy(seed: &str) -> u64 {
+    let mut hasher = SipHasher13::new_with_keys(0, 0);
+    hasher.write(seed.as_bytes());
+    hasher.write_u8(0xff);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use rand::seq::SliceRandom;
+    use crate::model::{draw_mission, draw_missions, resolve, Part, Resolved, ResolvedMission};
+    use crate::state::load_catalog;
+    use crate::validate;
+
+    // Captured from `DefaultHasher::new()` on rustc 1.95 before the switch
+    #[test]
+    fn legacy_seeds_hash_like_default_hasher() {
+        assert_eq!(hash("0"), 0xfc66_4701_4fb5_54e5);
+        assert_eq!(hash("12345"), 0x8b4f_0f5e_26e6_1d80);
+        assert_eq!(hash("ABCD"), 0xbd67_53e3_dce8_ee15);
+        assert_eq!(hash(""), 0x3040_6ea5_23c5_3def);
+        // Unknown prefixes are just legacy seeds
+        assert_eq!(hash("v2:12345"), 0xa1b9_b120_f5d5_8915);
+    }
+
+    #[test]
+    fn v1_seeds_use_fnv1a() {
+        assert_eq!(hash("v1:"), FNV_OFFSET_BASIS);
+        assert_eq!(hash("v1:12345"), 0xe575_e888_3c0f_89f8);
+    }
+
+    // The same draws the handlers make: solo picks 2, coop picks 1, then a random number
+    fn draws(seed: &str) -> (Vec<usize>, usize, u32) {
+        let pool: Vec<usize> = (0..10).collect();
+        let mut rng = rng(seed);
+        let picks = pool.choose_multiple(&mut rng, 2).cloned().collect();
+        let one = *pool.choose(&mut rng).unwrap();
+        (picks, one, rng.gen_range(1..=4))
+    }
+
+    #[test]
+    fn golden_draws() {
+        assert_eq!(draws("0"), (vec![6, 3], 8, 4));
+        assert_eq!(draws("12345"), (vec![2, 4], 2, 1));
+        assert_eq!(draws("hello world"), (vec![5, 4], 4, 1));
+        assert_eq!(draws("v1:12345"), (vec![2, 5], 1, 1));
+        assert_eq!(draws("v1:"), (vec![9, 6], 6, 3));
+    }
+
+    // What a player would read off a mission card
+    fn describe(mission: &ResolvedMission) -> String {
+        let names = |parts: &[Part]| parts.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", ");
+        let requirements: Vec<String> = mission.requirements.iter()
+            .map(|req| match req {
+                Resolved::Pick(_, parts) => names(parts),
+                Resolved::Number(n) => n.to_string(),
+                Resolved::Loadout(l) => format!("{} / {} / {} / {}", l.character.name, l.kart.name, l.wheel.name, l.glider.name),
+                Resolved::Players(players) => format!("players {:?}", players),
+                Resolved::Mission(_, nested) => format!("[{}]", describe(nested)),
+                Resolved::Checklist(checklist) => format!("{} of {}", checklist.target(), checklist.category.slug()),
+            })
+            .collect();
+        format!("{}: {}", mission.mission.name, requirements.join("; "))
+    }
+
+    // The shipped data, drawn the way the solo and coop pages draw it. Editing
+    // missions.json or catalog.json changes these on purpose; anything else
+    // that does breaks links people already shared.
+    fn mission_draws(seed: &str) -> (Vec<String>, String) {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let catalog = load_catalog(&root.join("catalog.json")).unwrap();
+        let content = std::fs::read_to_string(root.join("missions.json")).unwrap();
+        let data = validate::validate(&content, &catalog).unwrap();
+
+        let mut solo_rng = rng(seed);
+        let solo = draw_missions(&data.missions, 2, &mut solo_rng).into_iter()
+            .map(|m| describe(&resolve(m.clone(), &mut solo_rng, &data, &catalog)))
+            .collect();
+        let mut coop_rng = rng(seed);
+        let mission = draw_mission(&data.coop_granprix, &mut coop_rng).unwrap().clone();
+        (solo, describe(&resolve(mission, &mut coop_rng, &data, &catalog)))
+    }
+
+    #[test]
+    fn golden_mission_draws() {
+        assert_eq!(mission_draws("12345"), (
+            vec!["Too Shocked To Drink: ".to_string(), "Secret Santa: ".to_string()],
+            "Soul-locked Drinking (GP Edition): ".to_string(),
+        ));
+        assert_eq!(mission_draws("15"), (
+            vec!["Picky Drinker: Super Horn".to_string(), "Bumper Cars: ".to_string()],
+            "Mission Marathon: [Soul-locked Drinking: ]".to_string(),
+        ));
+        assert_eq!(mission_draws("87"), (
+            vec!["One in the Chamber: ".to_string(), "Randomizer: Yoshi / Inkstriker / Blue Standard / Paraglider".to_string()],
+            "Jack of All Trades: 22 of items".to_string(),
+        ));
+        assert_eq!(mission_draws("v1:12345"), (
+            vec!["Double or Nothing: ".to_string(), "Banker: ".to_string()],
+            "The Perfect Run: ".to_string(),
+        ));
+        assert_eq!(mission_draws("v1:8"), (
+            vec!["Picky Drinker: Golden Mushroom".to_string(), "Tavern Brawler: 1".to_string()],
+            "Blue%: ".to_string(),
+        ));
+        assert_eq!(mission_draws("v1:87"), (
+            vec!["Watch Your Back: ".to_string(), "Tavern Brawler: 4".to_string()],
+            "Squad Goals: Roy / Master Cycle Zero / Retro Off-Road / Super Glider".to_string(),
+        ));
+    }
+
+    #[test]
+    fn generated_seeds_are_current_version() {
+        assert!(generate().starts_with("v1:"));
+    }
+}
@@ -1,50 +1,703 @@
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 use std::collections::HashMap;
-use tokio::sync::broadcast;
-use crate::model::MissionsData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, mpsc};
+use crate::filter::MissionFilter;
+use crate::model::{Catalog, Category, ChecklistState, MissionsData};
+use crate::store::LobbyStore;
+use crate::validate;
 use serde::{Serialize, Deserialize};
 
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+const REAP_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Clone, Debug, Serialize)]
 pub enum LobbyEvent {
-    PlayerJoined(String),
-    PlayerLeft(String),
-    GameStarted,
+    PlayerJoined(PlayerInfo),
+    PlayerLeft(u32),
+    // Removed by the host; their own page is sent away, everyone else sees them leave
+    PlayerKicked(u32),
+    PlayerRenamed(PlayerInfo),
+    // Connection dropped or came back; the player stays in the lobby meanwhile
+    PlayerDisconnected(u32),
+    PlayerReconnected(u32),
+    // Host moved or the lobby was locked/unlocked
+    LobbyUpdated,
+    // Everyone follows the lobby to its new phase
+    PhaseChanged(LobbyStatus),
+    VoteCast,
+    // Someone ticked an item on this round's mission
+    ChecklistUpdated(Category),
+    LobbyClosed,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+/// Phases of a round. `Waiting` is the lobby between rounds; a round goes
+/// `RoleReveal` -> `Racing` -> `Voting` -> `Results`, then either back to
+/// `Waiting` or straight into the next round's `RoleReveal`.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
 pub enum LobbyStatus {
     Waiting,
-    Started,
+    RoleReveal,
+    // Lobbies saved before the round phases existed were simply "Started"
+    #[serde(alias = "Started")]
+    Racing,
+    /// Racing is over and everyone accuses someone
+    Voting,
+    Results,
+}
+
+impl LobbyStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            LobbyStatus::Waiting => "Lobby",
+            LobbyStatus::RoleReveal => "Role Reveal",
+            LobbyStatus::Racing => "Racing",
+            LobbyStatus::Voting => "Voting",
+            LobbyStatus::Results => "Results",
+        }
+    }
+}
+
+/// What other players get to know about someone: no token, no role.
+#[derive(Clone, Debug, Serialize)]
+pub struct PlayerInfo {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    #[default]
+    Innocent,
+    Traitor,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(from = "RawPlayer")]
 pub struct Player {
+    // Stable within the lobby, used for DOM ids so names can be anything
+    pub id: u32,
     pub name: String,
-    pub is_traitor: bool,
+    // Secret from the player's session cookie, never rendered
+    pub token: String,
+    pub role: Role,
+}
+
+// Lobbies saved before roles existed only have `is_traitor`
+#[derive(Deserialize)]
+struct RawPlayer {
+    #[serde(default)]
+    id: u32,
+    name: String,
+    #[serde(default)]
+    token: String,
+    #[serde(default)]
+    role: Option<Role>,
+    #[serde(default)]
+    is_traitor: bool,
+}
+
+impl From<RawPlayer> for Player {
+    fn from(raw: RawPlayer) -> Self {
+        let legacy = if raw.is_traitor { Role::Traitor } else { Role::Innocent };
+        Self { id: raw.id, name: raw.name, token: raw.token, role: raw.role.unwrap_or(legacy) }
+    }
+}
+
+/// How many traitors a round gets.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TraitorCount {
+    Fixed { count: usize },
+    /// Percentage of the players, rounded, at least one
+    Ratio { percent: u32 },
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct LobbySettings {
+    pub traitors: TraitorCount,
+    /// Now and then deal no traitors at all, or nobody but traitors
+    pub chaos: bool,
+    /// Which missions rounds can draw; the table size comes from the lobby
+    pub filter: MissionFilter,
 }
 
-#[derive(Clone, Debug)]
+impl Default for LobbySettings {
+    fn default() -> Self {
+        Self { traitors: TraitorCount::Fixed { count: 1 }, chaos: false, filter: MissionFilter::default() }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Lobby {
     pub players: Vec<Player>,
+    // Players whose connection dropped, kept so their session can rejoin with the same role
+    #[serde(default)]
+    pub departed: Vec<Player>,
+    #[serde(default)]
+    pub next_player_id: u32,
+    // Session token of the host. Empty for lobbies from before hosts existed,
+    // in which case the next player to join takes over.
+    #[serde(default)]
+    pub host: String,
+    // No new players while locked; players who dropped can still come back
+    #[serde(default)]
+    pub locked: bool,
+    // Session tokens the host banned; they can't join again
+    #[serde(default)]
+    pub banned: Vec<String>,
+    #[serde(default)]
+    pub settings: LobbySettings,
+    // Voter id -> accused player id, `None` for "there is no traitor"
+    #[serde(default)]
+    pub votes: HashMap<u32, Option<u32>>,
     pub status: LobbyStatus,
+    // Unix seconds when `status` last changed, for the spectator timer
+    #[serde(default = "unix_now")]
+    pub phase_started: u64,
     pub seed: String,
+    // Rounds started so far, the current one included
+    #[serde(default)]
+    pub round: u32,
+    // Players at the table when this round started. Mission draws use it so
+    // someone leaving mid-round doesn't change the mission under everyone.
+    #[serde(default)]
+    pub table_size: usize,
+    // Ids of the players dealt into this round. Anyone else sits it out, even
+    // after rejoining, and takes no part in the vote or the scoring.
+    #[serde(default)]
+    pub dealt: Vec<u32>,
+    // Player id -> points over all rounds
+    #[serde(default)]
+    pub scores: HashMap<u32, u32>,
+    // Checklist ticks for this round's mission
+    #[serde(default)]
+    pub checklists: ChecklistState,
+    // Unix seconds, used by the reaper
+    #[serde(default = "unix_now")]
+    pub created_at: u64,
+    #[serde(default = "unix_now")]
+    pub last_active: u64,
+    // Not persisted; a restored lobby gets a fresh channel and SSE clients resubscribe on reconnect
+    #[serde(skip, default = "lobby_channel")]
     pub tx: broadcast::Sender<LobbyEvent>,
+    // Open SSE streams per session token. A page change opens the new stream
+    // before the old one is noticed as closed, so presence can't be a flag.
+    #[serde(skip)]
+    pub connections: HashMap<String, usize>,
+    // Session token -> when its last stream closed, while it has none open
+    #[serde(skip)]
+    pub dropped: HashMap<String, Instant>,
+}
+
+impl Lobby {
+    pub fn new(seed: String) -> Self {
+        Self {
+            players: Vec::new(),
+            departed: Vec::new(),
+            next_player_id: 0,
+            host: String::new(),
+            locked: false,
+            banned: Vec::new(),
+            settings: LobbySettings::default(),
+            votes: HashMap::new(),
+            status: LobbyStatus::Waiting,
+            phase_started: unix_now(),
+            seed,
+            round: 0,
+            table_size: 0,
+            dealt: Vec::new(),
+            scores: HashMap::new(),
+            checklists: ChecklistState::new(),
+            created_at: unix_now(),
+            last_active: unix_now(),
+            tx: lobby_channel(),
+            connections: HashMap::new(),
+            dropped: HashMap::new(),
+        }
+    }
+
+    /// Adds a player under the next free id and tells everyone connected.
+    pub fn add_player(&mut self, name: String, token: String) {
+        let player = Player { id: self.next_player_id, name, token, role: Role::Innocent };
+        self.next_player_id += 1;
+        if self.host.is_empty() {
+            self.host = player.token.clone();
+        }
+        let _ = self.tx.send(LobbyEvent::PlayerJoined(player.info()));
+        self.players.push(player);
+    }
+
+    /// Brings back a player whose connection dropped. Returns whether anything changed.
+    pub fn rejoin(&mut self, token: &str) -> bool {
+        let Some(pos) = self.departed.iter().position(|p| p.token == token) else { return false };
+        let player = self.departed.remove(pos);
+        let _ = self.tx.send(LobbyEvent::PlayerJoined(player.info()));
+        self.players.push(player);
+        true
+    }
+
+    /// Counts a newly opened stream for `token`. A player coming back from a
+    /// dropped connection is shown as connected again.
+    pub fn connect(&mut self, token: &str) {
+        let open = self.connections.entry(token.to_string()).or_default();
+        *open += 1;
+        if *open == 1 && self.dropped.remove(token).is_some() {
+            if let Some(player) = self.players.iter().find(|p| p.token == token) {
+                let _ = self.tx.send(LobbyEvent::PlayerReconnected(player.id));
+            }
+        }
+    }
+
+    /// Counts a closed stream for `token`. When it was the last one the player
+    /// shows as disconnected until they come back or `expire` moves them out,
+    /// and the time they dropped is returned.
+    pub fn disconnect(&mut self, token: &str) -> Option<Instant> {
+        let open = self.connections.entry(token.to_string()).or_default();
+        *open = open.saturating_sub(1);
+        if *open > 0 {
+            return None;
+        }
+        self.connections.remove(token);
+        let now = Instant::now();
+        self.dropped.insert(token.to_string(), now);
+        if let Some(player) = self.players.iter().find(|p| p.token == token) {
+            let _ = self.tx.send(LobbyEvent::PlayerDisconnected(player.id));
+        }
+        Some(now)
+    }
+
+    /// Moves a player out of the lobby once they've been disconnected for `grace`.
+    /// Mid-round they stay on the roster so their role and vote still count.
+    /// Returns whether anything changed.
+    pub fn expire(&mut self, token: &str, grace: Duration) -> bool {
+        if self.status != LobbyStatus::Waiting {
+            return false;
+        }
+        if self.dropped.get(token).is_none_or(|since| since.elapsed() < grace) {
+            return false;
+        }
+        self.remove_dropped(token, grace)
+    }
+
+    /// Moves out everyone who's been disconnected for `grace`, whatever the phase.
+    /// For between rounds, when no role or vote depends on them any more.
+    /// Returns whether anything changed.
+    pub fn expire_dropped(&mut self, grace: Duration) -> bool {
+        let expired: Vec<String> = self.dropped.iter()
+            .filter(|(_, since)| since.elapsed() >= grace)
+            .map(|(token, _)| token.clone())
+            .collect();
+        let mut changed = false;
+        for token in expired {
+            changed |= self.remove_dropped(&token, grace);
+        }
+        changed
+    }
+
+    fn remove_dropped(&mut self, token: &str, grace: Duration) -> bool {
+        self.dropped.remove(token);
+        let Some(pos) = self.players.iter().position(|p| p.token == token) else { return false };
+        let player = self.players.remove(pos);
+        let _ = self.tx.send(LobbyEvent::PlayerLeft(player.id));
+        self.departed.push(player);
+        self.hand_off_host(grace);
+        true
+    }
+
+    /// Marks everyone as dropped as of now, for a lobby loaded back from the
+    /// store. Nobody has a stream open yet, so they get the usual grace to
+    /// reconnect before they're moved out.
+    pub fn restore(&mut self) {
+        let now = Instant::now();
+        for player in &self.players {
+            self.dropped.insert(player.token.clone(), now);
+        }
+    }
+
+    /// Passes hosting on when the host has left, or has been disconnected for
+    /// `grace`, so the lobby isn't stuck without anyone who can start it. The
+    /// connected player who's been in the lobby longest takes over.
+    /// Returns whether the host changed.
+    pub fn hand_off_host(&mut self, grace: Duration) -> bool {
+        let host_away = self.departed.iter().any(|p| p.token == self.host)
+            || self.dropped.get(&self.host).is_some_and(|since| since.elapsed() >= grace);
+        if !host_away {
+            return false;
+        }
+        let Some(next) = self.players.iter().find(|p| p.token != self.host && self.is_connected(&p.token)) else {
+            return false;
+        };
+        self.host = next.token.clone();
+        let _ = self.tx.send(LobbyEvent::LobbyUpdated);
+        true
+    }
+
+    /// Whether `token` has a stream open right now.
+    pub fn is_connected(&self, token: &str) -> bool {
+        self.connections.get(token).is_some_and(|&n| n > 0)
+    }
+
+    pub fn set_status(&mut self, status: LobbyStatus) {
+        self.status = status;
+        self.phase_started = unix_now();
+        let _ = self.tx.send(LobbyEvent::PhaseChanged(status));
+    }
+
+    /// Seed for everything dealt in the current round. The first round uses the
+    /// lobby seed itself, later ones derive from it.
+    pub fn round_seed(&self) -> String {
+        if self.round <= 1 {
+            self.seed.clone()
+        } else {
+            format!("{}:round:{}", self.seed, self.round)
+        }
+    }
+
+    /// The player holding `token`, connected or not.
+    pub fn member(&self, token: &str) -> Option<&Player> {
+        if token.is_empty() {
+            return None;
+        }
+        self.players.iter().chain(&self.departed).find(|p| p.token == token)
+    }
+
+    /// Anyone who's been in this lobby, connected or not.
+    pub fn player_by_id(&self, id: u32) -> Option<&Player> {
+        self.players.iter().chain(&self.departed).find(|p| p.id == id)
+    }
+
+    /// Whether the player was dealt into the current round. Lobbies saved
+    /// before this was tracked count everyone in.
+    pub fn is_dealt(&self, id: u32) -> bool {
+        self.dealt.is_empty() || self.dealt.contains(&id)
+    }
+
+    /// Everyone dealt into the current round, whether they're still here or not.
+    pub fn in_round(&self) -> impl Iterator<Item = &Player> {
+        self.players.iter().chain(&self.departed).filter(|p| self.is_dealt(p.id))
+    }
+
+    pub fn is_host(&self, token: &str) -> bool {
+        !token.is_empty() && self.host == token
+    }
+
+    pub fn host_player(&self) -> Option<&Player> {
+        self.players.iter().chain(&self.departed).find(|p| p.token == self.host)
+    }
+
+    /// Names are unique per lobby, ignoring case. Departed players keep theirs
+    /// so they can come back.
+    pub fn name_taken(&self, name: &str, except_token: &str) -> bool {
+        self.players.iter().chain(&self.departed)
+            .any(|p| p.token != except_token && p.name.to_lowercase() == name.to_lowercase())
+    }
+}
+
+impl Player {
+    pub fn info(&self) -> PlayerInfo {
+        PlayerInfo { id: self.id, name: self.name.clone() }
+    }
+}
+
+/// Ticks `slug` off, or unticks it if it already was.
+pub fn toggle_checklist(state: &mut ChecklistState, category: Category, slug: &str) {
+    let ticked = state.entry(category).or_default();
+    if !ticked.remove(slug) {
+        ticked.insert(slug.to_string());
+    }
+    if ticked.is_empty() {
+        state.remove(&category);
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn lobby_channel() -> broadcast::Sender<LobbyEvent> {
+    broadcast::channel(100).0
 }
 
 #[derive(Clone)]
 pub struct AppState {
-    pub missions: Arc<MissionsData>,
+    // Swapped wholesale on reload; handlers grab snapshots via `missions()` / `catalog()`
+    data: Arc<RwLock<GameData>>,
+    missions_path: PathBuf,
+    catalog_path: PathBuf,
     pub lobbies: Arc<RwLock<HashMap<String, Lobby>>>,
+    // Saves and removals go to a background writer, so nobody waits on the
+    // disk while holding the lobbies lock
+    store_tx: mpsc::UnboundedSender<StoreWrite>,
+    lobbies_reaped: Arc<AtomicU64>,
+    // Checklist ticks for seeded pages outside a lobby. Memory only, reaped like idle lobbies.
+    checklists: Arc<RwLock<HashMap<String, SeedChecklist>>>,
+    // How long a dropped player is shown as disconnected before they leave the lobby
+    pub reconnect_grace: Duration,
+    // Required by /admin/reload, which is off when there is none
+    pub admin_token: Option<String>,
+}
+
+struct SeedChecklist {
+    ticked: ChecklistState,
+    last_active: u64,
+    // Which category changed, so open pages re-fetch that checklist
+    tx: broadcast::Sender<Category>,
+}
+
+impl SeedChecklist {
+    fn new() -> Self {
+        Self { ticked: ChecklistState::new(), last_active: unix_now(), tx: broadcast::channel(100).0 }
+    }
+}
+
+enum StoreWrite {
+    Save(String, Box<Lobby>),
+    Remove(String),
+}
+
+// Writes happen one at a time in the order they were queued, so a lobby's
+// last save always wins and a removal is never undone by an earlier save
+fn spawn_store_writer(store: Arc<dyn LobbyStore>) -> mpsc::UnboundedSender<StoreWrite> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(write) = rx.recv().await {
+            let store = store.clone();
+            let written = tokio::task::spawn_blocking(move || match write {
+                StoreWrite::Save(room_id, lobby) => store.save(&room_id, &lobby)
+                    .map_err(|e| format!("Failed to save lobby {}: {}", room_id, e)),
+                StoreWrite::Remove(room_id) => store.remove(&room_id)
+                    .map_err(|e| format!("Failed to remove lobby {}: {}", room_id, e)),
+            }).await;
+            match written {
+                Ok(Err(e)) => tracing::error!("{}", e),
+                Err(e) => tracing::error!("Lobby store writer failed: {}", e),
+                Ok(Ok(())) => {}
+            }
+        }
+    });
+    tx
+}
+
+// Missions reference the catalog, so the two are always loaded and swapped together
+#[derive(Clone)]
+struct GameData {
+    missions: Arc<MissionsData>,
+    catalog: Arc<Catalog>,
 }
 
 impl AppState {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let content = fs::read_to_string("missions.json")?;
-        let missions: MissionsData = serde_json::from_str(&content)?;
+    pub fn new(
+        missions_path: PathBuf,
+        catalog_path: PathBuf,
+        store: Arc<dyn LobbyStore>,
+        reconnect_grace: Duration,
+        admin_token: Option<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = load_data(&missions_path, &catalog_path)?;
+        let mut lobbies = store.load_all()?;
+        for lobby in lobbies.values_mut() {
+            lobby.restore();
+        }
+        if !lobbies.is_empty() {
+            tracing::info!("Restored {} lobbies", lobbies.len());
+        }
         Ok(Self {
-            missions: Arc::new(missions),
-            lobbies: Arc::new(RwLock::new(HashMap::new())),
+            data: Arc::new(RwLock::new(data)),
+            missions_path,
+            catalog_path,
+            lobbies: Arc::new(RwLock::new(lobbies)),
+            store_tx: spawn_store_writer(store),
+            lobbies_reaped: Arc::new(AtomicU64::new(0)),
+            checklists: Arc::new(RwLock::new(HashMap::new())),
+            reconnect_grace,
+            admin_token,
         })
     }
+
+    /// Mark a lobby active and persist it after changing it. Only a copy is
+    /// queued here, the write happens in the background once the caller has let
+    /// go of the lock. Failures are logged, the game carries on in memory.
+    pub fn save_lobby(&self, room_id: &str, lobby: &mut Lobby) {
+        lobby.last_active = unix_now();
+        let _ = self.store_tx.send(StoreWrite::Save(room_id.to_string(), Box::new(lobby.clone())));
+    }
+
+    /// What has been ticked off on `seed` so far.
+    pub fn seed_checklists(&self, seed: &str) -> ChecklistState {
+        self.checklists.read().unwrap().get(seed).map(|c| c.ticked.clone()).unwrap_or_default()
+    }
+
+    /// Ticks `slug` off on `seed`, or unticks it if it already was. Returns the new state.
+    pub fn toggle_seed_checklist(&self, seed: &str, category: Category, slug: &str) -> ChecklistState {
+        let mut checklists = self.checklists.write().unwrap();
+        let checklist = checklists.entry(seed.to_string()).or_insert_with(SeedChecklist::new);
+        checklist.last_active = unix_now();
+        toggle_checklist(&mut checklist.ticked, category, slug);
+        let _ = checklist.tx.send(category);
+        checklist.ticked.clone()
+    }
+
+    /// Changes to the checklists on `seed`, for pages showing them live.
+    pub fn subscribe_seed_checklist(&self, seed: &str) -> broadcast::Receiver<Category> {
+        let mut checklists = self.checklists.write().unwrap();
+        checklists.entry(seed.to_string()).or_insert_with(SeedChecklist::new).tx.subscribe()
+    }
+
+    /// Snapshot of the currently loaded missions. Stays valid even if a reload happens mid-request.
+    pub fn missions(&self) -> Arc<MissionsData> {
+        self.data.read().unwrap().missions.clone()
+    }
+
+    /// Like `missions()`, narrowed down to what `filter` allows.
+    pub fn filtered_missions(&self, filter: &MissionFilter) -> Arc<MissionsData> {
+        let missions = self.missions();
+        if filter.is_empty() { missions } else { Arc::new(filter.apply(&missions)) }
+    }
+
+    pub fn catalog(&self) -> Arc<Catalog> {
+        self.data.read().unwrap().catalog.clone()
+    }
+
+    /// Re-read missions and catalog and swap them in. On error the old data keeps serving.
+    pub fn reload_missions(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let data = load_data(&self.missions_path, &self.catalog_path)?;
+        *self.data.write().unwrap() = data;
+        Ok(())
+    }
+
+    /// Poll the data files and reload whenever either modification time changes.
+    pub fn watch_missions(&self) {
+        let state = self.clone();
+        tokio::spawn(async move {
+            let mut last_modified = state.modified_times();
+            let mut interval = tokio::time::interval(WATCH_INTERVAL);
+            loop {
+                interval.tick().await;
+                let modified = state.modified_times();
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+
+                match state.reload_missions() {
+                    Ok(()) => tracing::info!("Reloaded {}", state.missions_path.display()),
+                    Err(e) => tracing::error!(
+                        "Rejected {}, keeping previous missions: {}",
+                        state.missions_path.display(),
+                        e
+                    ),
+                }
+            }
+        });
+    }
+
+    /// Periodically close lobbies nobody has touched in `idle_ttl` or that are older than `max_age`.
+    /// A lobby with an open SSE connection doesn't count as idle.
+    pub fn spawn_reaper(&self, idle_ttl: Duration, max_age: Duration) {
+        let state = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REAP_INTERVAL);
+            loop {
+                interval.tick().await;
+                state.reap_lobbies(idle_ttl.as_secs(), max_age.as_secs());
+                state.reap_checklists(idle_ttl.as_secs());
+            }
+        });
+    }
+
+    fn reap_lobbies(&self, idle_ttl: u64, max_age: u64) {
+        let now = unix_now();
+        let mut lobbies = self.lobbies.write().unwrap();
+        let expired: Vec<String> = lobbies.iter()
+            .filter(|(_, lobby)| {
+                let idle = lobby.tx.receiver_count() == 0 && now.saturating_sub(lobby.last_active) > idle_ttl;
+                idle || now.saturating_sub(lobby.created_at) > max_age
+            })
+            .map(|(room_id, _)| room_id.clone())
+            .collect();
+
+        for room_id in expired {
+            if let Some(lobby) = lobbies.remove(&room_id) {
+                // Connected clients get told before the channel goes away with the lobby
+                let _ = lobby.tx.send(LobbyEvent::LobbyClosed);
+            }
+            let _ = self.store_tx.send(StoreWrite::Remove(room_id.clone()));
+            self.lobbies_reaped.fetch_add(1, Ordering::Relaxed);
+            tracing::info!("Closed lobby {}", room_id);
+        }
+    }
+
+    fn reap_checklists(&self, idle_ttl: u64) {
+        let now = unix_now();
+        self.checklists.write().unwrap()
+            .retain(|_, checklist| {
+                checklist.tx.receiver_count() > 0 || now.saturating_sub(checklist.last_active) <= idle_ttl
+            });
+    }
+
+    /// Prometheus text exposition of lobby counts.
+    pub fn metrics(&self) -> String {
+        let lobbies = self.lobbies.read().unwrap();
+        let players: usize = lobbies.values().map(|l| l.players.len()).sum();
+        let connections: usize = lobbies.values().map(|l| l.tx.receiver_count()).sum();
+        format!(
+            "# HELP beerio_lobbies_live Traitor lobbies currently open.\n\
+             # TYPE beerio_lobbies_live gauge\n\
+             beerio_lobbies_live {}\n\
+             # HELP beerio_lobby_players Players across all open lobbies.\n\
+             # TYPE beerio_lobby_players gauge\n\
+             beerio_lobby_players {}\n\
+             # HELP beerio_lobby_connections Open lobby SSE connections.\n\
+             # TYPE beerio_lobby_connections gauge\n\
+             beerio_lobby_connections {}\n\
+             # HELP beerio_lobbies_reaped_total Lobbies closed by the reaper.\n\
+             # TYPE beerio_lobbies_reaped_total counter\n\
+             beerio_lobbies_reaped_total {}\n",
+            lobbies.len(),
+            players,
+            connections,
+            self.lobbies_reaped.load(Ordering::Relaxed),
+        )
+    }
+
+    fn modified_times(&self) -> (Option<SystemTime>, Option<SystemTime>) {
+        (modified_time(&self.missions_path), modified_time(&self.catalog_path))
+    }
+}
+
+pub fn load_catalog(path: &Path) -> Result<Catalog, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let catalog: Catalog = serde_json::from_str(&content)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    catalog.check()?;
+    Ok(catalog)
+}
+
+fn load_data(missions_path: &Path, catalog_path: &Path) -> Result<GameData, Box<dyn std::error::Error>> {
+    let catalog = load_catalog(catalog_path)?;
+
+    let content = fs::read_to_string(missions_path)
+        .map_err(|e| format!("{}: {}", missions_path.display(), e))?;
+    let missions = validate::validate(&content, &catalog).map_err(|issues| {
+        issues.iter()
+            .map(|issue| format!("{}: {}", missions_path.display(), issue))
+            .collect::<Vec<_>>()
+            .join("\n")
+    })?;
+
+    Ok(GameData {
+        missions: Arc::new(missions),
+        catalog: Arc::new(catalog),
+    })
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
 }
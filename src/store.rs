@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use crate::state::Lobby;
+
+/// Where lobbies are kept between restarts. Every mutation of a lobby is followed by a `save`.
+pub trait LobbyStore: Send + Sync {
+    fn load_all(&self) -> io::Result<HashMap<String, Lobby>>;
+    fn save(&self, room_id: &str, lobby: &Lobby) -> io::Result<()>;
+    fn remove(&self, room_id: &str) -> io::Result<()>;
+}
+
+/// Keeps nothing; lobbies die with the process like they used to.
+pub struct MemoryStore;
+
+impl LobbyStore for MemoryStore {
+    fn load_all(&self) -> io::Result<HashMap<String, Lobby>> {
+        Ok(HashMap::new())
+    }
+
+    fn save(&self, _room_id: &str, _lobby: &Lobby) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn remove(&self, _room_id: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// One JSON file per lobby in a directory, e.g. `data/lobbies/ABCD.json`.
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, room_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", room_id))
+    }
+}
+
+impl LobbyStore for FileStore {
+    fn load_all(&self) -> io::Result<HashMap<String, Lobby>> {
+        let mut lobbies = HashMap::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|e| e != "json") {
+                continue;
+            }
+            let Some(room_id) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+
+            // A single corrupt file shouldn't keep the server from starting
+            match fs::read_to_string(&path).map(|c| serde_json::from_str::<Lobby>(&c)) {
+                Ok(Ok(lobby)) => {
+                    lobbies.insert(room_id.to_string(), lobby);
+                }
+                Ok(Err(e)) => tracing::warn!("Skipping lobby {}: {}", path.display(), e),
+                Err(e) => tracing::warn!("Skipping lobby {}: {}", path.display(), e),
+            }
+        }
+        Ok(lobbies)
+    }
+
+    // Write to a temp file and rename so a crash mid-write never leaves half a lobby behind
+    fn save(&self, room_id: &str, lobby: &Lobby) -> io::Result<()> {
+        let path = self.path(room_id);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(lobby)?)?;
+        fs::rename(tmp, path)
+    }
+
+    fn remove(&self, room_id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(room_id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,1502 @@
+use axum::{
+    extract::{State, Form, Path, Query},
+    response::{Html, Redirect, IntoResponse, Response},
+    routing::{get, post},
+    http::{HeaderMap, StatusCode},
+    Router,
+};
+use axum::response::sse::{Event, Sse};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use askama::Template;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt;
+use std::convert::Infallible;
+
+use crate::model::{self, Category, Checklist, Difficulty, MissionPool, Resolved, ResolvedMission};
+use crate::state::{self, AppState, Lobby, LobbySettings, Player, PlayerInfo, Role, TraitorCount, LobbyStatus, LobbyEvent};
+use crate::bans;
+use crate::checklist::{self, ChecklistQuery};
+use crate::filter::{FilterQuery, MissionFilter};
+use crate::{render_nested, render_response, seed};
+
+// Identifies a browser across lobbies. The token is only ever compared server-side,
+// so knowing someone's name is no longer enough to see their role.
+const SESSION_COOKIE: &str = "beerio_session";
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/traitor/create", get(traitor_setup).post(traitor_create))
+        .route("/traitor/{room_id}/join", get(traitor_join_view).post(traitor_join_action))
+        .route("/traitor/{room_id}", get(traitor_lobby_view))
+        .route("/traitor/{room_id}/sse", get(traitor_lobby_sse))
+        .route("/traitor/{room_id}/controls", get(traitor_controls_view))
+        .route("/traitor/{room_id}/start", post(traitor_start))
+        .route("/traitor/{room_id}/reset", post(traitor_reset))
+        .route("/traitor/{room_id}/lock", post(traitor_lock))
+        .route("/traitor/{room_id}/kick/{player_id}", post(traitor_kick))
+        .route("/traitor/{room_id}/ban/{player_id}", post(traitor_ban))
+        .route("/traitor/{room_id}/removed", get(traitor_removed_view))
+        .route("/traitor/{room_id}/host/{player_id}", post(traitor_transfer_host))
+        .route("/traitor/{room_id}/name", post(traitor_change_name_action))
+        .route("/traitor/{room_id}/role", get(traitor_role_view))
+        .route("/traitor/{room_id}/checklist/sse", get(traitor_checklist_sse))
+        .route("/traitor/{room_id}/checklist/{category}", get(traitor_checklist_view))
+        .route("/traitor/{room_id}/checklist/{category}/{slug}", post(traitor_checklist_toggle))
+        .route("/traitor/{room_id}/race", post(traitor_start_race))
+        .route("/traitor/{room_id}/voting", post(traitor_start_voting))
+        .route("/traitor/{room_id}/vote", get(traitor_vote_view).post(traitor_vote_action))
+        .route("/traitor/{room_id}/votes", get(traitor_vote_progress))
+        .route("/traitor/{room_id}/reveal", post(traitor_reveal))
+        .route("/traitor/{room_id}/results", get(traitor_results_view))
+        .route("/traitor/{room_id}/next", post(traitor_next_round))
+        .route("/traitor/{room_id}/watch", get(traitor_watch_view))
+        .route("/traitor/{room_id}/watch/board", get(traitor_watch_board))
+}
+
+fn session_token(jar: &CookieJar) -> Option<String> {
+    jar.get(SESSION_COOKIE)
+        .map(|c| c.value().to_string())
+        .filter(|t| !t.is_empty())
+}
+
+// Reuse the browser's token if it has one, otherwise issue a new one
+fn ensure_session(jar: CookieJar) -> (CookieJar, String) {
+    if let Some(token) = session_token(&jar) {
+        return (jar, token);
+    }
+    let token: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    let cookie = Cookie::build((SESSION_COOKIE, token.clone()))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .permanent();
+    (jar.add(cookie), token)
+}
+
+#[derive(Template)]
+#[template(path = "traitor_setup.html")]
+struct TraitorSetupTemplate {
+    ratio: bool,
+    count: usize,
+    percent: u32,
+    chaos: bool,
+    filter: MissionFilter,
+}
+
+impl TraitorSetupTemplate {
+    fn new(settings: &LobbySettings) -> Self {
+        let (ratio, count, percent) = match settings.traitors {
+            TraitorCount::Fixed { count } => (false, count, 25),
+            TraitorCount::Ratio { percent } => (true, 1, percent),
+        };
+        Self { ratio, count, percent, chaos: settings.chaos, filter: settings.filter.clone() }
+    }
+}
+
+async fn traitor_setup(headers: HeaderMap) -> Html<String> {
+    let template = TraitorSetupTemplate::new(&LobbySettings::default());
+    render_response(headers, template.render().unwrap(), None)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SetupForm {
+    mode: String,
+    count: Option<usize>,
+    percent: Option<u32>,
+    chaos: Option<String>,
+    // Mission filter, the table size comes from the lobby itself
+    tags: String,
+    exclude: String,
+    max_difficulty: String,
+}
+
+impl SetupForm {
+    fn settings(&self) -> LobbySettings {
+        let traitors = match self.mode.as_str() {
+            "ratio" => TraitorCount::Ratio { percent: self.percent.unwrap_or(25).clamp(1, 100) },
+            _ => TraitorCount::Fixed { count: self.count.unwrap_or(1).max(1) },
+        };
+        let filter = FilterQuery {
+            tags: self.tags.clone(),
+            exclude: self.exclude.clone(),
+            max_difficulty: self.max_difficulty.clone(),
+            ..FilterQuery::default()
+        };
+        LobbySettings { traitors, chaos: self.chaos.is_some(), filter: filter.filter() }
+    }
+}
+
+async fn traitor_create(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Form(form): Form<SetupForm>,
+) -> impl IntoResponse {
+    // Use a short random string for room ID
+    let room_id: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(4)
+        .map(char::from)
+        .collect::<String>()
+        .to_uppercase();
+
+    // Whoever creates the lobby runs it
+    let (jar, token) = ensure_session(jar);
+    let mut lobby = Lobby::new(seed::generate());
+    lobby.host = token;
+    lobby.settings = form.settings();
+
+    state.save_lobby(&room_id, &mut lobby);
+    state.lobbies.write().unwrap().insert(room_id.clone(), lobby);
+
+    (jar, Redirect::to(&format!("/traitor/{}", room_id)))
+}
+
+#[derive(Deserialize)]
+struct JoinForm {
+    name: String,
+}
+
+async fn traitor_join_view(
+    Path(room_id): Path<String>,
+) ->  impl IntoResponse {
+    Redirect::to(&format!("/traitor/{}", room_id))
+}
+
+async fn traitor_join_action(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Form(form): Form<JoinForm>,
+) -> Response {
+    tracing::debug!("Join action triggered for room {}", room_id);
+    let mut lobbies = state.lobbies.write().unwrap();
+
+    if let Some(lobby) = lobbies.get_mut(&room_id) {
+        if lobby.status != LobbyStatus::Waiting {
+             return (StatusCode::BAD_REQUEST, "Game already started").into_response();
+        }
+
+        let (jar, token) = ensure_session(jar);
+        if lobby.banned.contains(&token) {
+            return (jar, removed_page(&room_id, headers)).into_response();
+        }
+        if lobby.rejoin(&token) {
+            state.save_lobby(&room_id, lobby);
+        }
+        if !lobby.players.iter().any(|p| p.token == token) {
+            if lobby.locked {
+                return (jar, lobby_page(&room_id, lobby, &token, "The lobby is locked".to_string(), headers)).into_response();
+            }
+            let name = match normalize_name(&form.name) {
+                Ok(name) if lobby.name_taken(&name, &token) => Err(format!("{} is already taken", name)),
+                other => other,
+            };
+            let name = match name {
+                Ok(name) => name,
+                Err(error) => return (jar, lobby_page(&room_id, lobby, &token, error, headers)).into_response(),
+            };
+
+            lobby.departed.retain(|p| p.token != token);
+            lobby.add_player(name, token);
+            state.save_lobby(&room_id, lobby);
+        }
+
+        return (jar, Redirect::to(&format!("/traitor/{}", room_id))).into_response();
+    }
+
+    (StatusCode::NOT_FOUND, "Lobby not found").into_response()
+}
+
+#[derive(Template)]
+#[template(path = "traitor_lobby.html")]
+struct TraitorLobbyTemplate {
+    room_id: String,
+    player_id: Option<u32>,
+    player_name: String,
+    // Everyone in the lobby and whether they're connected right now
+    players: Vec<(Player, bool)>,
+    name_error: String,
+    controls: String,
+    // Empty until the first round is played
+    scoreboard: Vec<(String, u32)>,
+}
+
+#[derive(Template)]
+#[template(path = "partials/traitor_controls.html")]
+struct TraitorControlsTemplate {
+    room_id: String,
+    joined: bool,
+    is_host: bool,
+    host_name: String,
+    locked: bool,
+    rules: String,
+    players: Vec<Player>,
+}
+
+impl TraitorControlsTemplate {
+    fn new(room_id: &str, lobby: &Lobby, token: &str) -> Self {
+        Self {
+            room_id: room_id.to_string(),
+            joined: !token.is_empty() && lobby.players.iter().any(|p| p.token == token),
+            is_host: lobby.is_host(token),
+            host_name: lobby.host_player().map(|p| p.name.clone()).unwrap_or_default(),
+            locked: lobby.locked,
+            rules: describe_settings(&lobby.settings),
+            players: lobby.players.clone(),
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "partials/traitor_player.html")]
+struct TraitorPlayerTemplate {
+    player: PlayerInfo,
+    you: bool,
+    connected: bool,
+}
+
+#[derive(Template)]
+#[template(source = "<div id=\"player-{{ id }}\" hx-swap-oob=\"delete\"></div>", ext = "html")]
+struct TraitorPlayerLeftTemplate {
+    id: u32,
+}
+
+#[derive(Template)]
+#[template(source = "<span id=\"player-{{ id }}-presence\" hx-swap-oob=\"innerHTML\">{% if !connected %}disconnected{% endif %}</span>", ext = "html")]
+struct TraitorPlayerPresenceTemplate {
+    id: u32,
+    connected: bool,
+}
+
+#[derive(Template)]
+#[template(source = "<span id=\"player-{{ player.id }}-name\" hx-swap-oob=\"innerHTML\">{{ player.name }}</span>", ext = "html")]
+struct TraitorPlayerRenamedTemplate {
+    player: PlayerInfo,
+}
+
+const MAX_NAME_LEN: usize = 20;
+
+// Collapses runs of whitespace and drops control characters, so "  Bob\t " and "Bob" are the same player
+fn normalize_name(raw: &str) -> Result<String, String> {
+    let name = raw.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    if name.is_empty() {
+        return Err("Please enter a name".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(format!("Names can be at most {} characters", MAX_NAME_LEN));
+    }
+    Ok(name)
+}
+
+// The lobby as seen by the holder of `token`, with a message next to the name form.
+// A rejected name keeps the URL where it was instead of pushing the form's action.
+fn lobby_page(room_id: &str, lobby: &Lobby, token: &str, name_error: String, headers: HeaderMap) -> Response {
+    let player = lobby.players.iter().find(|p| !token.is_empty() && p.token == token);
+    let rejected = !name_error.is_empty();
+    let template = TraitorLobbyTemplate {
+        room_id: room_id.to_string(),
+        player_id: player.map(|p| p.id),
+        player_name: player.map(|p| p.name.clone()).unwrap_or_default(),
+        // Your own stream may not be open yet while this page loads
+        players: lobby.players.iter()
+            .map(|p| (p.clone(), p.token == token || lobby.is_connected(&p.token)))
+            .collect(),
+        name_error,
+        controls: TraitorControlsTemplate::new(room_id, lobby, token).render().unwrap(),
+        scoreboard: if lobby.round > 0 { scoreboard(lobby) } else { Vec::new() },
+    };
+    // We might want to pass view_name: "traitor" here if we want the pill selector to work in the lobby
+    // but for now, the user mostly cares about the swipe navigation between the main game modes.
+    let page = render_response(headers, template.render().unwrap(), None);
+    if rejected {
+        ([("HX-Push-Url", "false")], page).into_response()
+    } else {
+        page.into_response()
+    }
+}
+
+async fn traitor_lobby_view(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let mut lobbies = state.lobbies.write().unwrap();
+    if let Some(lobby) = lobbies.get_mut(&room_id) {
+        // Without a session in this lobby we treat them as "Joining"
+        // and reuse the lobby template with the join form
+        let token = session_token(&jar).unwrap_or_default();
+        if lobby.banned.contains(&token) {
+            return removed_page(&room_id, headers);
+        }
+
+        // Reconnect logic: bring back a player whose SSE stream dropped
+        if lobby.rejoin(&token) {
+            state.save_lobby(&room_id, lobby);
+        }
+
+        let joined = !token.is_empty() && lobby.players.iter().any(|p| p.token == token);
+        if joined && lobby.status != LobbyStatus::Waiting {
+            return Redirect::to(&phase_url(&room_id, &lobby.status)).into_response();
+        }
+
+        return lobby_page(&room_id, lobby, &token, String::new(), headers);
+    }
+    (StatusCode::NOT_FOUND, "Lobby not found").into_response()
+}
+
+struct PlayerLeaveGuard {
+    state: AppState,
+    room_id: String,
+    token: String,
+}
+
+impl Drop for PlayerLeaveGuard {
+    fn drop(&mut self) {
+        let state = self.state.clone();
+        let room_id = self.room_id.clone();
+        let token = self.token.clone();
+
+        // Spawn async task for cleanup since Drop is sync
+        tokio::spawn(async move {
+            let since = {
+                let mut lobbies = state.lobbies.write().unwrap();
+                let Some(lobby) = lobbies.get_mut(&room_id) else { return };
+                match lobby.disconnect(&token) {
+                    Some(since) => since,
+                    None => return,
+                }
+            };
+
+            watch_dropped(state, room_id, token, since).await;
+        });
+    }
+}
+
+/// Starts the grace timers for players in restored lobbies, who all count as
+/// dropped until their pages reconnect.
+pub fn spawn_grace_timers(state: &AppState) {
+    let lobbies = state.lobbies.read().unwrap();
+    for (room_id, lobby) in lobbies.iter() {
+        for (token, &since) in &lobby.dropped {
+            tokio::spawn(watch_dropped(state.clone(), room_id.clone(), token.clone(), since));
+        }
+    }
+}
+
+// Gives a player who dropped at `since` a chance to reconnect. Mid-round they
+// never leave, so keep checking until they're back or the lobby returns to waiting.
+async fn watch_dropped(state: AppState, room_id: String, token: String, since: Instant) {
+    loop {
+        tokio::time::sleep(state.reconnect_grace).await;
+        let mut lobbies = state.lobbies.write().unwrap();
+        let Some(lobby) = lobbies.get_mut(&room_id) else { return };
+        if lobby.dropped.get(&token) != Some(&since) {
+            return;
+        }
+        // Kicked while away
+        if !lobby.players.iter().any(|p| p.token == token) {
+            lobby.dropped.remove(&token);
+            return;
+        }
+        // Mid-round a host who dropped stays on the roster, but someone
+        // still has to be able to move the round on
+        if lobby.hand_off_host(state.reconnect_grace) {
+            tracing::info!("Lobby {} has a new host after the old one disconnected", room_id);
+            state.save_lobby(&room_id, lobby);
+        }
+        if lobby.expire(&token, state.reconnect_grace) {
+            tracing::info!("Player left lobby {} after disconnecting", room_id);
+            state.save_lobby(&room_id, lobby);
+            return;
+        }
+    }
+}
+
+// SSE Endpoint
+async fn traitor_lobby_sse(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+) -> impl IntoResponse {
+    let mut lobbies = state.lobbies.write().unwrap();
+
+    if let Some(lobby) = lobbies.get_mut(&room_id) {
+        let rx = lobby.tx.subscribe();
+        let stream = BroadcastStream::new(rx);
+
+        // Presence is only tracked for joined players; anyone else just watches
+        let token = session_token(&jar).unwrap_or_default();
+        if lobby.rejoin(&token) {
+            state.save_lobby(&room_id, lobby);
+        }
+        let player = lobby.players.iter().find(|p| !token.is_empty() && p.token == token);
+        let viewer = player.map(|p| p.id);
+        let guard = player.map(|player| {
+            tracing::debug!("Player {} connected to lobby {}", player.id, room_id);
+            Arc::new(PlayerLeaveGuard {
+                state: state.clone(),
+                room_id: room_id.clone(),
+                token: player.token.clone(),
+            })
+        });
+        if guard.is_some() {
+            lobby.connect(&token);
+        }
+
+        // Capture guard in closure
+        let stream = stream.map(move |msg| {
+             let _keep_alive = guard.as_ref();
+             match msg {
+                 Ok(LobbyEvent::PlayerJoined(player)) => {
+                     let html = TraitorPlayerTemplate { player, you: false, connected: true }.render().unwrap();
+                     Ok::<Event, Infallible>(Event::default().event("player_joined").data(html))
+                 },
+                 Ok(LobbyEvent::PlayerKicked(id)) if viewer == Some(id) => {
+                     Ok::<Event, Infallible>(Event::default().event("kicked").data("kicked"))
+                 },
+                 Ok(LobbyEvent::PlayerLeft(id) | LobbyEvent::PlayerKicked(id)) => {
+                     let html = TraitorPlayerLeftTemplate { id }.render().unwrap();
+                     Ok::<Event, Infallible>(Event::default().event("player_left").data(html))
+                 },
+                 Ok(LobbyEvent::PlayerRenamed(player)) => {
+                     let html = TraitorPlayerRenamedTemplate { player }.render().unwrap();
+                     Ok::<Event, Infallible>(Event::default().event("player_renamed").data(html))
+                 },
+                 Ok(LobbyEvent::PlayerDisconnected(id)) => {
+                     let html = TraitorPlayerPresenceTemplate { id, connected: false }.render().unwrap();
+                     Ok::<Event, Infallible>(Event::default().event("player_presence").data(html))
+                 },
+                 Ok(LobbyEvent::PlayerReconnected(id)) => {
+                     let html = TraitorPlayerPresenceTemplate { id, connected: true }.render().unwrap();
+                     Ok::<Event, Infallible>(Event::default().event("player_presence").data(html))
+                 },
+                 Ok(LobbyEvent::LobbyUpdated) => {
+                     Ok::<Event, Infallible>(Event::default().event("lobby_updated").data("updated"))
+                 },
+                 Ok(LobbyEvent::PhaseChanged(_)) => {
+                     // Pages re-fetch the lobby URL, which sends each player to the page for the new phase
+                     Ok::<Event, Infallible>(Event::default().event("phase_changed").data("changed"))
+                 },
+                 Ok(LobbyEvent::VoteCast) => {
+                     Ok::<Event, Infallible>(Event::default().event("vote_cast").data("voted"))
+                 },
+                 Ok(LobbyEvent::ChecklistUpdated(category)) => {
+                     Ok::<Event, Infallible>(Event::default().event(checklist::event_name(category)).data("updated"))
+                 },
+                 Ok(LobbyEvent::LobbyClosed) => {
+                     Ok::<Event, Infallible>(Event::default().event("lobby_closed").data("closed"))
+                 },
+                 Err(_) => Ok::<Event, Infallible>(Event::default()) // Ignore lag errors
+             }
+        });
+
+        Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::default()).into_response()
+    } else {
+        let (_, rx) = broadcast::channel::<LobbyEvent>(1);
+        let stream = BroadcastStream::new(rx).map(|_| Ok::<Event, Infallible>(Event::default()));
+        Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::default()).into_response()
+    }
+}
+
+// The lobby if the caller's session is its host, otherwise the response to send back
+fn hosted_lobby<'a>(
+    lobbies: &'a mut HashMap<String, Lobby>,
+    room_id: &str,
+    jar: &CookieJar,
+) -> Result<&'a mut Lobby, (StatusCode, &'static str)> {
+    let lobby = lobbies.get_mut(room_id).ok_or((StatusCode::NOT_FOUND, "Lobby not found"))?;
+    if !lobby.is_host(&session_token(jar).unwrap_or_default()) {
+        return Err((StatusCode::FORBIDDEN, "Only the host can do that"));
+    }
+    Ok(lobby)
+}
+
+async fn traitor_controls_view(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+) -> impl IntoResponse {
+    let lobbies = state.lobbies.read().unwrap();
+    if let Some(lobby) = lobbies.get(&room_id) {
+        let token = session_token(&jar).unwrap_or_default();
+        let template = TraitorControlsTemplate::new(&room_id, lobby, &token);
+        return Html(template.render().unwrap()).into_response();
+    }
+    (StatusCode::NOT_FOUND, "Lobby not found").into_response()
+}
+
+async fn traitor_start(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+) -> Response {
+    let mut lobbies = state.lobbies.write().unwrap();
+    let lobby = match hosted_lobby(&mut lobbies, &room_id, &jar) {
+        Ok(lobby) => lobby,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if lobby.status != LobbyStatus::Waiting {
+        return (StatusCode::OK, "Already started").into_response();
+    }
+    // The phase controls live on the players' pages, so a host who never
+    // joined would have no way to move the round along
+    if !lobby.players.iter().any(|p| p.token == lobby.host) {
+        return (StatusCode::BAD_REQUEST, "Join the lobby before starting").into_response();
+    }
+
+    let started = start_round(lobby, bans::banned(&jar), state.reconnect_grace);
+    state.save_lobby(&room_id, lobby);
+    if let Err(rejection) = started {
+        return rejection.into_response();
+    }
+
+    // Return 200 OK
+    StatusCode::OK.into_response()
+}
+
+// Chance per round that chaos mode skips the configured count
+const CHAOS_CHANCE: f64 = 0.2;
+
+fn traitor_count<R: Rng>(settings: &LobbySettings, players: usize, rng: &mut R) -> usize {
+    if settings.chaos && rng.gen_bool(CHAOS_CHANCE) {
+        // Nobody or everybody, equally likely
+        return if rng.gen_bool(0.5) { 0 } else { players };
+    }
+    match settings.traitors {
+        TraitorCount::Fixed { count } => count.min(players),
+        TraitorCount::Ratio { percent } => ((players * percent as usize + 50) / 100).clamp(1, players),
+    }
+}
+
+// Next round: new seed, fresh roles, everyone to the reveal.
+// The host's bans are taken along so every player draws the same mission.
+// Players gone for longer than `grace` are moved out first so they aren't dealt in.
+fn start_round(lobby: &mut Lobby, banned: Vec<String>, grace: Duration) -> Result<(), (StatusCode, &'static str)> {
+    lobby.expire_dropped(grace);
+    if lobby.players.is_empty() { // Allow 1 for testing, though traitor implies >1
+        return Err((StatusCode::BAD_REQUEST, "Not enough players"));
+    }
+    lobby.round += 1;
+    lobby.table_size = lobby.players.len();
+    lobby.settings.filter.banned = banned;
+    lobby.votes.clear();
+    lobby.checklists.clear();
+    let mut rng = seed::rng(&format!("{}:roles", lobby.round_seed()));
+    deal_roles(lobby, &mut rng);
+    lobby.set_status(LobbyStatus::RoleReveal);
+    Ok(())
+}
+
+// Only the players here now are dealt in. Anyone who left keeps no role from
+// an earlier round, so a rejoin can't bring an old traitor card back.
+fn deal_roles<R: Rng>(lobby: &mut Lobby, rng: &mut R) {
+    let count = traitor_count(&lobby.settings, lobby.players.len(), rng);
+    for player in lobby.players.iter_mut().chain(lobby.departed.iter_mut()) {
+        player.role = Role::Innocent;
+    }
+    lobby.dealt = lobby.players.iter().map(|p| p.id).collect();
+    for player in lobby.players.choose_multiple(rng, count).map(|p| p.id).collect::<Vec<_>>() {
+        if let Some(player) = lobby.players.iter_mut().find(|p| p.id == player) {
+            player.role = Role::Traitor;
+        }
+    }
+}
+
+fn describe_settings(settings: &LobbySettings) -> String {
+    let traitors = match settings.traitors {
+        TraitorCount::Fixed { count: 1 } => "1 traitor".to_string(),
+        TraitorCount::Fixed { count } => format!("{} traitors", count),
+        TraitorCount::Ratio { percent } => format!("{}% traitors", percent),
+    };
+    let mut rules = traitors;
+    if settings.chaos {
+        rules.push_str(", chaos mode");
+    }
+    if !settings.filter.is_empty() {
+        rules.push_str(&format!(", missions: {}", settings.filter.describe()));
+    }
+    rules
+}
+
+// Back to the lobby with the same players, roles are dealt again on the next start
+async fn traitor_reset(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+) -> Response {
+    let mut lobbies = state.lobbies.write().unwrap();
+    let lobby = match hosted_lobby(&mut lobbies, &room_id, &jar) {
+        Ok(lobby) => lobby,
+        Err(rejection) => return rejection.into_response(),
+    };
+    for player in lobby.players.iter_mut().chain(lobby.departed.iter_mut()) {
+        player.role = Role::Innocent;
+    }
+    lobby.votes.clear();
+    lobby.set_status(LobbyStatus::Waiting);
+    state.save_lobby(&room_id, lobby);
+    StatusCode::OK.into_response()
+}
+
+async fn traitor_lock(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+) -> Response {
+    let mut lobbies = state.lobbies.write().unwrap();
+    let lobby = match hosted_lobby(&mut lobbies, &room_id, &jar) {
+        Ok(lobby) => lobby,
+        Err(rejection) => return rejection.into_response(),
+    };
+    lobby.locked = !lobby.locked;
+    let _ = lobby.tx.send(LobbyEvent::LobbyUpdated);
+    state.save_lobby(&room_id, lobby);
+    StatusCode::OK.into_response()
+}
+
+async fn traitor_kick(
+    State(state): State<AppState>,
+    Path((room_id, player_id)): Path<(String, u32)>,
+    jar: CookieJar,
+) -> Response {
+    remove_player(&state, &room_id, player_id, &jar, false)
+}
+
+// A kick they can't undo by joining again from the same browser
+async fn traitor_ban(
+    State(state): State<AppState>,
+    Path((room_id, player_id)): Path<(String, u32)>,
+    jar: CookieJar,
+) -> Response {
+    remove_player(&state, &room_id, player_id, &jar, true)
+}
+
+fn remove_player(state: &AppState, room_id: &str, player_id: u32, jar: &CookieJar, ban: bool) -> Response {
+    let mut lobbies = state.lobbies.write().unwrap();
+    let lobby = match hosted_lobby(&mut lobbies, room_id, jar) {
+        Ok(lobby) => lobby,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if lobby.host_player().is_some_and(|p| p.id == player_id) {
+        return (StatusCode::BAD_REQUEST, "The host can't kick themselves").into_response();
+    }
+    let Some(token) = lobby.player_by_id(player_id).map(|p| p.token.clone()) else {
+        return (StatusCode::NOT_FOUND, "Player not in lobby").into_response();
+    };
+    // Gone from the roster and from `departed`, so no reconnect brings them back
+    lobby.players.retain(|p| p.id != player_id);
+    lobby.departed.retain(|p| p.id != player_id);
+    lobby.votes.remove(&player_id);
+    if ban {
+        lobby.banned.push(token);
+    }
+    let _ = lobby.tx.send(LobbyEvent::PlayerKicked(player_id));
+
+    // They may have been the last one holding up the vote
+    if lobby.status == LobbyStatus::Voting && all_voted(lobby) {
+        finish_voting(lobby);
+    }
+    state.save_lobby(room_id, lobby);
+    StatusCode::OK.into_response()
+}
+
+#[derive(Template)]
+#[template(path = "traitor_removed.html")]
+struct TraitorRemovedTemplate {
+    room_id: String,
+}
+
+fn removed_page(room_id: &str, headers: HeaderMap) -> Response {
+    let template = TraitorRemovedTemplate { room_id: room_id.to_string() };
+    render_response(headers, template.render().unwrap(), None).into_response()
+}
+
+async fn traitor_removed_view(Path(room_id): Path<String>, headers: HeaderMap) -> Response {
+    removed_page(&room_id, headers)
+}
+
+async fn traitor_transfer_host(
+    State(state): State<AppState>,
+    Path((room_id, player_id)): Path<(String, u32)>,
+    jar: CookieJar,
+) -> Response {
+    let mut lobbies = state.lobbies.write().unwrap();
+    let lobby = match hosted_lobby(&mut lobbies, &room_id, &jar) {
+        Ok(lobby) => lobby,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let Some(player) = lobby.players.iter().find(|p| p.id == player_id) else {
+        return (StatusCode::NOT_FOUND, "Player not in lobby").into_response();
+    };
+    lobby.host = player.token.clone();
+    let _ = lobby.tx.send(LobbyEvent::LobbyUpdated);
+    state.save_lobby(&room_id, lobby);
+    StatusCode::OK.into_response()
+}
+
+#[derive(Deserialize)]
+struct NameChangeForm {
+    name: String,
+}
+
+async fn traitor_change_name_action(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Form(form): Form<NameChangeForm>,
+) -> impl IntoResponse {
+    let Some(token) = session_token(&jar) else {
+        return StatusCode::FORBIDDEN.into_response();
+    };
+    let mut lobbies = state.lobbies.write().unwrap();
+    if let Some(lobby) = lobbies.get_mut(&room_id) {
+
+        // Find player
+        if let Some(pos) = lobby.players.iter().position(|p| p.token == token) {
+            let new_name = match normalize_name(&form.name) {
+                Ok(name) if lobby.name_taken(&name, &token) => Err(format!("{} is already taken", name)),
+                other => other,
+            };
+            let new_name = match new_name {
+                Ok(name) => name,
+                Err(error) => return lobby_page(&room_id, lobby, &token, error, headers),
+            };
+            if new_name != lobby.players[pos].name {
+                tracing::info!("Player {} in lobby {} renamed", lobby.players[pos].id, room_id);
+                lobby.players[pos].name = new_name;
+                let _ = lobby.tx.send(LobbyEvent::PlayerRenamed(lobby.players[pos].info()));
+                state.save_lobby(&room_id, lobby);
+
+                // Re-render the lobby with the new name
+                return Redirect::to(&format!("/traitor/{}", room_id)).into_response();
+            }
+        }
+    }
+    StatusCode::OK.into_response()
+}
+
+#[derive(Template)]
+#[template(path = "traitor_role.html")]
+struct TraitorRoleTemplate {
+    room_id: String,
+    round: u32,
+    // Roles have been seen and the race is on
+    racing: bool,
+    is_host: bool,
+    role: Role,
+    // Traitors know each other, except in chaos mode where that would give it away
+    fellow_traitors: Vec<String>,
+    // False for someone who rejoined after the deal; they sit the round out
+    dealt_in: bool,
+    player_name: String,
+    data: ResolvedMission,
+    // Rendered requirements of the mission, checklists tickable by the whole lobby
+    requirements: String,
+    // Rendered objective card, only ever filled in for traitors
+    objective: Option<String>,
+}
+
+async fn traitor_role_view(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let lobbies = state.lobbies.read().unwrap();
+    if let Some(lobby) = lobbies.get(&room_id) {
+        // Only the holder of the player's session token gets to see the role
+        let token = session_token(&jar).unwrap_or_default();
+        if !matches!(lobby.status, LobbyStatus::RoleReveal | LobbyStatus::Racing) {
+            return Redirect::to(&phase_url(&room_id, &lobby.status)).into_response();
+        }
+        if let Some(player) = lobby.member(&token) {
+
+            // Get mission using this round's seed
+            let round_seed = lobby.round_seed();
+            let mut rng = seed::rng(&round_seed);
+
+            // Traitor mode uses coop missions "for now we can just use the coop missions"
+            // A filter that leaves nothing to race for is ignored rather than stalling the round
+            let mut filter = lobby.settings.filter.clone();
+            // Lobbies saved before table sizes were recorded skip the player count
+            filter.players = Some(lobby.table_size).filter(|&n| n > 0);
+            let mut missions = state.filtered_missions(&filter);
+            if missions.coop_granprix.is_empty() {
+                missions = state.missions();
+            }
+            let mission = model::draw_mission(&missions.coop_granprix, &mut rng).unwrap().clone();
+            let mut mission = model::resolve(mission, &mut rng, &missions, &state.catalog());
+            mission.attach_checklists(&checklist_url(&room_id), &lobby.checklists);
+
+            // Each traitor's objective comes from their own stream so refreshing shows the same one
+            let objective = (player.role == Role::Traitor).then(|| {
+                let mut rng = seed::rng(&format!("{}:objective:{}", round_seed, player.id));
+                // Objectives rarely carry the tags a filter asks for, so an emptied pool falls back too
+                let objectives = if missions.traitor_objectives.is_empty() { state.missions() } else { missions.clone() };
+                model::draw_mission(&objectives.traitor_objectives, &mut rng).map(|m| {
+                    let resolved = model::resolve(m.clone(), &mut rng, &missions, &state.catalog());
+                    render_nested(&MissionPool::TraitorObjectives, &resolved)
+                })
+            }).flatten();
+
+            let template = TraitorRoleTemplate {
+                room_id: room_id.clone(),
+                round: lobby.round,
+                racing: lobby.status == LobbyStatus::Racing,
+                is_host: lobby.is_host(&token),
+                dealt_in: lobby.is_dealt(player.id),
+                role: player.role,
+                fellow_traitors: if player.role == Role::Traitor && !lobby.settings.chaos {
+                    lobby.in_round()
+                        .filter(|p| p.role == Role::Traitor && p.id != player.id)
+                        .map(|p| p.name.clone())
+                        .collect()
+                } else {
+                    Vec::new()
+                },
+                player_name: player.name.clone(),
+                requirements: RequirementsTemplate { data: &mission }.render().unwrap(),
+                data: mission,
+                objective,
+            };
+            return render_response(headers, template.render().unwrap(), None).into_response();
+        }
+        return (StatusCode::FORBIDDEN, "Player not in lobby").into_response();
+    }
+    (StatusCode::NOT_FOUND, "Lobby not found").into_response()
+}
+
+#[derive(Template)]
+#[template(source = "{% for req in data.requirements %}{% include \"partials/requirement.html\" %}{% endfor %}", ext = "html")]
+struct RequirementsTemplate<'a> {
+    data: &'a ResolvedMission,
+}
+
+// Checklists on the round's mission are shared by the whole lobby
+fn checklist_url(room_id: &str) -> String {
+    format!("/traitor/{}/checklist", room_id)
+}
+
+async fn traitor_checklist_view(
+    State(state): State<AppState>,
+    Path((room_id, category)): Path<(String, Category)>,
+    Query(query): Query<ChecklistQuery>,
+) -> Response {
+    let lobbies = state.lobbies.read().unwrap();
+    let Some(lobby) = lobbies.get(&room_id) else {
+        return (StatusCode::NOT_FOUND, "Lobby not found").into_response();
+    };
+    let html = checklist::render(&state.catalog(), category, query.goal, checklist_url(&room_id), &lobby.checklists);
+    Html(html).into_response()
+}
+
+async fn traitor_checklist_toggle(
+    State(state): State<AppState>,
+    Path((room_id, category, slug)): Path<(String, Category, String)>,
+    Query(query): Query<ChecklistQuery>,
+    jar: CookieJar,
+) -> Response {
+    let mut lobbies = state.lobbies.write().unwrap();
+    let Some(lobby) = lobbies.get_mut(&room_id) else {
+        return (StatusCode::NOT_FOUND, "Lobby not found").into_response();
+    };
+    if lobby.member(&session_token(&jar).unwrap_or_default()).is_none() {
+        return (StatusCode::FORBIDDEN, "Player not in lobby").into_response();
+    }
+    let catalog = state.catalog();
+    if !Checklist::new(category, None, &catalog).contains(&slug) {
+        return (StatusCode::NOT_FOUND, "Not on this checklist").into_response();
+    }
+    state::toggle_checklist(&mut lobby.checklists, category, &slug);
+    let _ = lobby.tx.send(LobbyEvent::ChecklistUpdated(category));
+    state.save_lobby(&room_id, lobby);
+
+    let html = checklist::render(&catalog, category, query.goal, checklist_url(&room_id), &lobby.checklists);
+    Html(html).into_response()
+}
+
+// Separate from the lobby stream so checklists don't count towards presence
+async fn traitor_checklist_sse(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+) -> impl IntoResponse {
+    let rx = match state.lobbies.read().unwrap().get(&room_id) {
+        Some(lobby) => lobby.tx.subscribe(),
+        None => broadcast::channel::<LobbyEvent>(1).1,
+    };
+    let stream = BroadcastStream::new(rx).filter_map(|msg| match msg {
+        Ok(LobbyEvent::ChecklistUpdated(category)) => {
+            Some(Ok::<Event, Infallible>(Event::default().event(checklist::event_name(category)).data("updated")))
+        }
+        _ => None,
+    });
+    Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::default())
+}
+
+// Where a player belongs while the lobby is in `status`
+fn phase_url(room_id: &str, status: &LobbyStatus) -> String {
+    match status {
+        LobbyStatus::Waiting => format!("/traitor/{}", room_id),
+        LobbyStatus::RoleReveal | LobbyStatus::Racing => format!("/traitor/{}/role", room_id),
+        LobbyStatus::Voting => format!("/traitor/{}/vote", room_id),
+        LobbyStatus::Results => format!("/traitor/{}/results", room_id),
+    }
+}
+
+async fn traitor_start_race(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+) -> Response {
+    let mut lobbies = state.lobbies.write().unwrap();
+    let lobby = match hosted_lobby(&mut lobbies, &room_id, &jar) {
+        Ok(lobby) => lobby,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if lobby.status != LobbyStatus::RoleReveal {
+        return (StatusCode::BAD_REQUEST, "Roles aren't being revealed").into_response();
+    }
+    lobby.set_status(LobbyStatus::Racing);
+    state.save_lobby(&room_id, lobby);
+    StatusCode::OK.into_response()
+}
+
+async fn traitor_start_voting(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+) -> Response {
+    let mut lobbies = state.lobbies.write().unwrap();
+    let lobby = match hosted_lobby(&mut lobbies, &room_id, &jar) {
+        Ok(lobby) => lobby,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if lobby.status != LobbyStatus::Racing {
+        return (StatusCode::BAD_REQUEST, "No race to vote on").into_response();
+    }
+    lobby.votes.clear();
+    lobby.set_status(LobbyStatus::Voting);
+    state.save_lobby(&room_id, lobby);
+    StatusCode::OK.into_response()
+}
+
+#[derive(Template)]
+#[template(path = "traitor_vote.html")]
+struct TraitorVoteTemplate {
+    room_id: String,
+    is_host: bool,
+    options: String,
+    progress: String,
+}
+
+#[derive(Template)]
+#[template(path = "partials/traitor_vote_options.html")]
+struct TraitorVoteOptionsTemplate {
+    room_id: String,
+    // (player, whether it's the current vote)
+    candidates: Vec<(PlayerInfo, bool)>,
+    nobody: bool,
+    // Not dealt into this round, so no vote either
+    sitting_out: bool,
+}
+
+impl TraitorVoteOptionsTemplate {
+    fn new(room_id: &str, lobby: &Lobby, voter: &Player) -> Self {
+        let choice = lobby.votes.get(&voter.id);
+        Self {
+            room_id: room_id.to_string(),
+            candidates: lobby.players.iter()
+                .filter(|p| p.id != voter.id && lobby.is_dealt(p.id))
+                .map(|p| (p.info(), choice == Some(&Some(p.id))))
+                .collect(),
+            nobody: choice == Some(&None),
+            sitting_out: !lobby.is_dealt(voter.id),
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "partials/traitor_vote_progress.html")]
+struct TraitorVoteProgressTemplate {
+    // (name, has voted)
+    voters: Vec<(String, bool)>,
+    voted: usize,
+}
+
+impl TraitorVoteProgressTemplate {
+    fn new(lobby: &Lobby) -> Self {
+        let voters: Vec<(String, bool)> = lobby.players.iter()
+            .filter(|p| lobby.is_dealt(p.id))
+            .map(|p| (p.name.clone(), lobby.votes.contains_key(&p.id)))
+            .collect();
+        let voted = voters.iter().filter(|(_, v)| *v).count();
+        Self { voters, voted }
+    }
+}
+
+async fn traitor_vote_view(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+    headers: HeaderMap,
+) -> Response {
+    let lobbies = state.lobbies.read().unwrap();
+    let Some(lobby) = lobbies.get(&room_id) else {
+        return (StatusCode::NOT_FOUND, "Lobby not found").into_response();
+    };
+    if lobby.status != LobbyStatus::Voting {
+        return Redirect::to(&phase_url(&room_id, &lobby.status)).into_response();
+    }
+    let token = session_token(&jar).unwrap_or_default();
+    let Some(voter) = lobby.member(&token) else {
+        return (StatusCode::FORBIDDEN, "Player not in lobby").into_response();
+    };
+    let template = TraitorVoteTemplate {
+        room_id: room_id.clone(),
+        is_host: lobby.is_host(&token),
+        options: TraitorVoteOptionsTemplate::new(&room_id, lobby, voter).render().unwrap(),
+        progress: TraitorVoteProgressTemplate::new(lobby).render().unwrap(),
+    };
+    render_response(headers, template.render().unwrap(), None).into_response()
+}
+
+#[derive(Deserialize)]
+struct VoteForm {
+    // A player id, or "nobody"
+    suspect: String,
+}
+
+async fn traitor_vote_action(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+    Form(form): Form<VoteForm>,
+) -> Response {
+    let mut lobbies = state.lobbies.write().unwrap();
+    let Some(lobby) = lobbies.get_mut(&room_id) else {
+        return (StatusCode::NOT_FOUND, "Lobby not found").into_response();
+    };
+    if lobby.status != LobbyStatus::Voting {
+        return (StatusCode::BAD_REQUEST, "Voting is closed").into_response();
+    }
+    let token = session_token(&jar).unwrap_or_default();
+    let Some(voter) = lobby.member(&token).map(|p| p.id) else {
+        return (StatusCode::FORBIDDEN, "Player not in lobby").into_response();
+    };
+    if !lobby.is_dealt(voter) {
+        return (StatusCode::FORBIDDEN, "You're sitting this round out").into_response();
+    }
+    let suspect = match form.suspect.as_str() {
+        "nobody" => None,
+        id => match id.parse::<u32>() {
+            Ok(id) if id != voter && lobby.player_by_id(id).is_some_and(|p| lobby.is_dealt(p.id)) => Some(id),
+            _ => return (StatusCode::BAD_REQUEST, "Unknown suspect").into_response(),
+        },
+    };
+    lobby.votes.insert(voter, suspect);
+    let _ = lobby.tx.send(LobbyEvent::VoteCast);
+
+    // Reveal as soon as everyone still here has voted
+    if all_voted(lobby) {
+        finish_voting(lobby);
+    }
+    state.save_lobby(&room_id, lobby);
+
+    let voter = lobby.player_by_id(voter).unwrap();
+    Html(TraitorVoteOptionsTemplate::new(&room_id, lobby, voter).render().unwrap()).into_response()
+}
+
+async fn traitor_vote_progress(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+) -> Response {
+    let lobbies = state.lobbies.read().unwrap();
+    match lobbies.get(&room_id) {
+        Some(lobby) => Html(TraitorVoteProgressTemplate::new(lobby).render().unwrap()).into_response(),
+        None => (StatusCode::NOT_FOUND, "Lobby not found").into_response(),
+    }
+}
+
+// Host closes voting early, e.g. when someone left without voting
+async fn traitor_reveal(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+) -> Response {
+    let mut lobbies = state.lobbies.write().unwrap();
+    let lobby = match hosted_lobby(&mut lobbies, &room_id, &jar) {
+        Ok(lobby) => lobby,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if lobby.status != LobbyStatus::Voting {
+        return (StatusCode::BAD_REQUEST, "Voting is closed").into_response();
+    }
+    finish_voting(lobby);
+    state.save_lobby(&room_id, lobby);
+    StatusCode::OK.into_response()
+}
+
+// Points for being on the winning side; a traitor who gets away with it earns more
+const WIN_POINTS: u32 = 1;
+const ESCAPE_POINTS: u32 = 2;
+
+// Whether everyone still here who was dealt in has voted
+fn all_voted(lobby: &Lobby) -> bool {
+    lobby.players.iter().filter(|p| lobby.is_dealt(p.id)).all(|p| lobby.votes.contains_key(&p.id))
+}
+
+fn finish_voting(lobby: &mut Lobby) {
+    let caught = tally(lobby).caught;
+    let winners: Vec<(u32, u32)> = lobby.in_round()
+        .filter_map(|p| match (p.role, caught) {
+            (Role::Innocent, true) => Some((p.id, WIN_POINTS)),
+            (Role::Traitor, false) => Some((p.id, ESCAPE_POINTS)),
+            _ => None,
+        })
+        .collect();
+    for (id, points) in winners {
+        *lobby.scores.entry(id).or_default() += points;
+    }
+    lobby.set_status(LobbyStatus::Results);
+}
+
+// Everyone who's played in the lobby, best first
+fn scoreboard(lobby: &Lobby) -> Vec<(String, u32)> {
+    let mut rows: Vec<(String, u32)> = lobby.players.iter().chain(&lobby.departed)
+        .map(|p| (p.name.clone(), lobby.scores.get(&p.id).copied().unwrap_or(0)))
+        .collect();
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    rows
+}
+
+/// Outcome of a vote. The group wins when a single suspect got the most votes
+/// and was a traitor, or when "no traitor" won and there really was none.
+struct Tally {
+    // (suspect, votes), most votes first
+    rows: Vec<(String, usize)>,
+    accused: Option<String>,
+    caught: bool,
+}
+
+fn tally(lobby: &Lobby) -> Tally {
+    let mut counts: Vec<(Option<u32>, usize)> = Vec::new();
+    for suspect in lobby.votes.values() {
+        match counts.iter_mut().find(|(s, _)| s == suspect) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((*suspect, 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let label = |suspect: Option<u32>| match suspect {
+        Some(id) => lobby.player_by_id(id).map(|p| p.name.clone()).unwrap_or_else(|| "Someone who left".to_string()),
+        None => "No traitor".to_string(),
+    };
+    let top = match counts.as_slice() {
+        [(suspect, _)] => Some(*suspect),
+        [(suspect, first), (_, second), ..] if first > second => Some(*suspect),
+        _ => None,
+    };
+    let any_traitors = lobby.in_round().any(|p| p.role == Role::Traitor);
+    let caught = match top {
+        Some(Some(id)) => lobby.player_by_id(id).is_some_and(|p| p.role == Role::Traitor),
+        Some(None) => !any_traitors,
+        None => false,
+    };
+
+    Tally {
+        rows: counts.iter().map(|(s, n)| (label(*s), *n)).collect(),
+        accused: top.map(label),
+        caught,
+    }
+}
+
+#[derive(Template)]
+#[template(path = "traitor_results.html")]
+struct TraitorResultsTemplate {
+    room_id: String,
+    round: u32,
+    is_host: bool,
+    scoreboard: Vec<(String, u32)>,
+    tally: Tally,
+    roles: Vec<(String, Role)>,
+    // The side that drinks: traitors if they were caught, innocents otherwise
+    losers: Vec<String>,
+}
+
+async fn traitor_results_view(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+    headers: HeaderMap,
+) -> Response {
+    let lobbies = state.lobbies.read().unwrap();
+    let Some(lobby) = lobbies.get(&room_id) else {
+        return (StatusCode::NOT_FOUND, "Lobby not found").into_response();
+    };
+    // Roles stay secret until the votes are in
+    if lobby.status != LobbyStatus::Results {
+        return Redirect::to(&phase_url(&room_id, &lobby.status)).into_response();
+    }
+    let token = session_token(&jar).unwrap_or_default();
+    let tally = tally(lobby);
+    let everyone = || lobby.in_round();
+    let losing_role = if tally.caught { Role::Traitor } else { Role::Innocent };
+    let template = TraitorResultsTemplate {
+        room_id: room_id.clone(),
+        round: lobby.round,
+        is_host: lobby.is_host(&token),
+        scoreboard: scoreboard(lobby),
+        roles: everyone().map(|p| (p.name.clone(), p.role)).collect(),
+        losers: everyone().filter(|p| p.role == losing_role).map(|p| p.name.clone()).collect(),
+        tally,
+    };
+    render_response(headers, template.render().unwrap(), None).into_response()
+}
+
+async fn traitor_next_round(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    jar: CookieJar,
+) -> Response {
+    let mut lobbies = state.lobbies.write().unwrap();
+    let lobby = match hosted_lobby(&mut lobbies, &room_id, &jar) {
+        Ok(lobby) => lobby,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if lobby.status != LobbyStatus::Results {
+        return (StatusCode::BAD_REQUEST, "The round isn't over yet").into_response();
+    }
+    let started = start_round(lobby, bans::banned(&jar), state.reconnect_grace);
+    state.save_lobby(&room_id, lobby);
+    match started {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+#[derive(Template)]
+#[template(path = "traitor_watch.html")]
+struct TraitorWatchTemplate {
+    room_id: String,
+    board: String,
+}
+
+// Everything a TV screen shows. Built from public state only: roles are read
+// solely once the lobby is in `Results`.
+#[derive(Template)]
+#[template(path = "partials/traitor_watch_board.html")]
+struct TraitorWatchBoardTemplate {
+    room_id: String,
+    phase: &'static str,
+    round: u32,
+    phase_started: u64,
+    // (name, connected, has voted), votes only matter while voting
+    players: Vec<(String, bool, bool)>,
+    voting: bool,
+    tally: Option<Tally>,
+    roles: Vec<(String, Role)>,
+    scoreboard: Vec<(String, u32)>,
+}
+
+impl TraitorWatchBoardTemplate {
+    fn new(room_id: &str, lobby: &Lobby) -> Self {
+        let revealed = lobby.status == LobbyStatus::Results;
+        Self {
+            room_id: room_id.to_string(),
+            phase: lobby.status.label(),
+            round: lobby.round,
+            phase_started: lobby.phase_started,
+            players: lobby.players.iter()
+                .map(|p| (p.name.clone(), lobby.is_connected(&p.token), lobby.votes.contains_key(&p.id)))
+                .collect(),
+            voting: lobby.status == LobbyStatus::Voting,
+            tally: revealed.then(|| tally(lobby)),
+            roles: if revealed {
+                lobby.in_round().map(|p| (p.name.clone(), p.role)).collect()
+            } else {
+                Vec::new()
+            },
+            scoreboard: if lobby.round > 0 { scoreboard(lobby) } else { Vec::new() },
+        }
+    }
+}
+
+// The spectator screen never joins, so it has no presence and no controls
+async fn traitor_watch_view(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let lobbies = state.lobbies.read().unwrap();
+    let Some(lobby) = lobbies.get(&room_id) else {
+        return (StatusCode::NOT_FOUND, "Lobby not found").into_response();
+    };
+    let template = TraitorWatchTemplate {
+        room_id: room_id.clone(),
+        board: TraitorWatchBoardTemplate::new(&room_id, lobby).render().unwrap(),
+    };
+    render_response(headers, template.render().unwrap(), None).into_response()
+}
+
+async fn traitor_watch_board(
+    State(state): State<AppState>,
+    Path(room_id): Path<String>,
+) -> Response {
+    let lobbies = state.lobbies.read().unwrap();
+    match lobbies.get(&room_id) {
+        Some(lobby) => Html(TraitorWatchBoardTemplate::new(&room_id, lobby).render().unwrap()).into_response(),
+        None => (StatusCode::NOT_FOUND, "Lobby not found").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ann, Bob, Cat and Dan with ids 0..4, Cat the only traitor
+    fn lobby() -> Lobby {
+        let mut lobby = Lobby::new("v1:1".to_string());
+        for name in ["Ann", "Bob", "Cat", "Dan"] {
+            lobby.add_player(name.to_string(), format!("{}-token", name));
+        }
+        lobby.host = "Ann-token".to_string();
+        set_traitors(&mut lobby, &[2]);
+        lobby.round = 1;
+        lobby.status = LobbyStatus::Voting;
+        lobby
+    }
+
+    fn set_traitors(lobby: &mut Lobby, traitors: &[u32]) {
+        for player in lobby.players.iter_mut() {
+            player.role = if traitors.contains(&player.id) { Role::Traitor } else { Role::Innocent };
+        }
+    }
+
+    fn vote(lobby: &mut Lobby, votes: &[(u32, Option<u32>)]) {
+        lobby.votes = votes.iter().copied().collect();
+    }
+
+    fn score(lobby: &Lobby, id: u32) -> u32 {
+        lobby.scores.get(&id).copied().unwrap_or(0)
+    }
+
+    #[test]
+    fn majority_on_a_traitor_catches_them() {
+        let mut lobby = lobby();
+        vote(&mut lobby, &[(0, Some(2)), (1, Some(2)), (2, Some(0)), (3, None)]);
+        let tally = tally(&lobby);
+        assert!(tally.caught);
+        assert_eq!(tally.accused.as_deref(), Some("Cat"));
+        assert_eq!(tally.rows[0], ("Cat".to_string(), 2));
+
+        finish_voting(&mut lobby);
+        assert_eq!(lobby.status, LobbyStatus::Results);
+        assert_eq!([0, 1, 2, 3].map(|id| score(&lobby, id)), [WIN_POINTS, WIN_POINTS, 0, WIN_POINTS]);
+    }
+
+    #[test]
+    fn a_tie_at_the_top_lets_the_traitor_escape() {
+        let mut lobby = lobby();
+        vote(&mut lobby, &[(0, Some(2)), (1, Some(2)), (2, Some(0)), (3, Some(0))]);
+        let tally = tally(&lobby);
+        assert!(!tally.caught);
+        assert_eq!(tally.accused, None);
+
+        finish_voting(&mut lobby);
+        assert_eq!([0, 1, 2, 3].map(|id| score(&lobby, id)), [0, 0, ESCAPE_POINTS, 0]);
+    }
+
+    #[test]
+    fn accusing_an_innocent_lets_the_traitor_escape() {
+        let mut lobby = lobby();
+        vote(&mut lobby, &[(0, Some(1)), (1, Some(0)), (2, Some(1)), (3, Some(1))]);
+        assert!(!tally(&lobby).caught);
+    }
+
+    #[test]
+    fn no_traitor_is_right_only_when_there_was_none() {
+        let mut lobby = lobby();
+        vote(&mut lobby, &[(0, None), (1, None), (2, None), (3, Some(2))]);
+        assert_eq!(tally(&lobby).accused.as_deref(), Some("No traitor"));
+        assert!(!tally(&lobby).caught);
+
+        set_traitors(&mut lobby, &[]);
+        assert!(tally(&lobby).caught);
+        finish_voting(&mut lobby);
+        assert_eq!([0, 1, 2, 3].map(|id| score(&lobby, id)), [WIN_POINTS; 4]);
+    }
+
+    #[test]
+    fn all_traitors_can_only_lose_by_being_named() {
+        let mut lobby = lobby();
+        set_traitors(&mut lobby, &[0, 1, 2, 3]);
+        vote(&mut lobby, &[(0, None), (1, None), (2, None), (3, None)]);
+        assert!(!tally(&lobby).caught);
+        finish_voting(&mut lobby);
+        assert_eq!([0, 1, 2, 3].map(|id| score(&lobby, id)), [ESCAPE_POINTS; 4]);
+    }
+
+    #[test]
+    fn traitor_counts_stay_within_the_table() {
+        let mut rng = seed::rng("v1:1");
+        let fixed = |count| LobbySettings { traitors: TraitorCount::Fixed { count }, ..LobbySettings::default() };
+        let ratio = |percent| LobbySettings { traitors: TraitorCount::Ratio { percent }, ..LobbySettings::default() };
+        assert_eq!(traitor_count(&fixed(3), 2, &mut rng), 2);
+        assert_eq!(traitor_count(&ratio(25), 6, &mut rng), 2);
+        assert_eq!(traitor_count(&ratio(10), 3, &mut rng), 1);
+        assert_eq!(traitor_count(&ratio(100), 5, &mut rng), 5);
+
+        // Chaos only ever swaps the usual count for none or everyone
+        let chaos = LobbySettings { chaos: true, ..fixed(1) };
+        let counts: Vec<usize> = (0..200).map(|_| traitor_count(&chaos, 5, &mut rng)).collect();
+        assert!(counts.iter().all(|&n| n == 0 || n == 1 || n == 5));
+        assert!(counts.contains(&0) && counts.contains(&1) && counts.contains(&5));
+    }
+
+    #[test]
+    fn a_departed_traitor_sits_the_next_round_out() {
+        let mut lobby = lobby();
+        lobby.status = LobbyStatus::Results;
+        lobby.connect("Cat-token");
+        lobby.disconnect("Cat-token");
+        start_round(&mut lobby, Vec::new(), Duration::ZERO).unwrap();
+
+        assert_eq!(lobby.departed.len(), 1);
+        let cat = lobby.player_by_id(2).unwrap();
+        assert_eq!(cat.role, Role::Innocent);
+        assert!(!lobby.is_dealt(2));
+        assert_eq!(lobby.table_size, 3);
+
+        // Cat coming back mid-round doesn't get a say in this one
+        assert!(lobby.rejoin("Cat-token"));
+        set_traitors(&mut lobby, &[]);
+        lobby.status = LobbyStatus::Voting;
+        vote(&mut lobby, &[(0, None), (1, None), (3, None)]);
+        assert!(all_voted(&lobby));
+        assert!(tally(&lobby).caught);
+
+        finish_voting(&mut lobby);
+        assert_eq!([0, 1, 2, 3].map(|id| score(&lobby, id)), [WIN_POINTS, WIN_POINTS, 0, WIN_POINTS]);
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::model::{Catalog, Category, Mission, MissionPool, MissionsData, Requirement};
+
+const POOLS: [(MissionPool, &str); 4] = [
+    (MissionPool::Missions, "missions"),
+    (MissionPool::CoopGranprix, "coop_granprix"),
+    (MissionPool::CoopSingle, "coop_single"),
+    (MissionPool::TraitorObjectives, "traitor_objectives"),
+];
+
+/// One problem in missions.json. `line` is 1-based when it could be located.
+#[derive(Debug)]
+pub struct Issue {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Parse and check missions.json, collecting every problem rather than stopping at the first.
+pub fn validate(source: &str, catalog: &Catalog) -> Result<MissionsData, Vec<Issue>> {
+    let data: MissionsData = serde_json::from_str(source).map_err(|e| {
+        vec![Issue { line: Some(e.line()), message: e.to_string() }]
+    })?;
+
+    let mut issues = Vec::new();
+    // Names are looked up case-insensitively by /{seed}/mission/{name}, so compare them that way
+    let mut seen: HashMap<String, (&str, Option<usize>)> = HashMap::new();
+
+    for (pool, key) in POOLS {
+        let missions = data.pool(pool);
+        let lines = locate(source, key, missions);
+        // Without objectives traitors just sabotage freely
+        if missions.is_empty() && pool != MissionPool::TraitorObjectives {
+            issues.push(Issue { line: lines.pool, message: format!("{} must not be empty", key) });
+        }
+
+        for (mission, line) in missions.iter().zip(lines.missions) {
+            let issue = |message: String| Issue { line, message: format!("{}: {}", mission.name, message) };
+
+            if mission.name.trim().is_empty() {
+                issues.push(Issue { line, message: format!("{}: mission without a name", key) });
+            }
+            if mission.name.contains('/') {
+                issues.push(issue("names can't contain '/', the mission link would break".to_string()));
+            }
+            match seen.get(&mission.name.to_lowercase()) {
+                Some((other_key, other_line)) => issues.push(issue(format!(
+                    "duplicate name, already used in {}{}",
+                    other_key,
+                    other_line.map(|l| format!(" on line {}", l)).unwrap_or_default()
+                ))),
+                None => {
+                    seen.insert(mission.name.to_lowercase(), (key, line));
+                }
+            }
+
+            if mission.weight == 0 {
+                issues.push(issue("weight must be at least 1, ban a mission to never draw it".to_string()));
+            }
+            if let Some(count) = mission.player_count {
+                if count.min == Some(0) || count.max == Some(0) {
+                    issues.push(issue("player_count needs at least one player".to_string()));
+                } else if let Some((min, max)) = count.min.zip(count.max).filter(|(min, max)| min > max) {
+                    issues.push(issue(format!("player_count min {} is above max {}", min, max)));
+                }
+            }
+
+            for req in &mission.requirements {
+                if let Some(message) = check_requirement(req, pool, catalog, &data) {
+                    issues.push(issue(message));
+                }
+            }
+        }
+    }
+
+    if issues.is_empty() { Ok(data) } else { Err(issues) }
+}
+
+fn check_requirement(req: &Requirement, pool: MissionPool, catalog: &Catalog, data: &MissionsData) -> Option<String> {
+    match *req {
+        Requirement::Pick { from, count } => {
+            let available = catalog.entries(from).len();
+            (count == 0 || count > available)
+                .then(|| format!("can't pick {} from {:?}, it has {} entries", count, from, available))
+        }
+        Requirement::Number { min, max } => {
+            (min > max).then(|| format!("number range {}..={} is empty", min, max))
+        }
+        Requirement::Players { count, of } => {
+            (count == 0 || count > of).then(|| format!("can't pick {} of {} players", count, of))
+        }
+        Requirement::Mission { pool: nested } => {
+            if nested == pool {
+                Some(format!("draws a nested mission from its own pool ({:?})", pool))
+            } else if nested == MissionPool::TraitorObjectives {
+                Some("traitor objectives are secret, they can't be drawn as nested missions".to_string())
+            } else if data.pool(nested).is_empty() {
+                Some(format!("draws a nested mission from {:?}, which is empty", nested))
+            } else {
+                None
+            }
+        }
+        Requirement::Checklist { from, goal } => {
+            let available = catalog.entries(from).len();
+            // Collecting the rare items is a single-race challenge
+            if from == Category::GachaItems && pool != MissionPool::CoopSingle {
+                Some("gacha item checklists only belong in coop_single".to_string())
+            } else if goal.is_some_and(|goal| goal == 0 || goal > available) {
+                Some(format!("checklist goal of {} can't be met, {:?} has {} entries", goal.unwrap(), from, available))
+            } else {
+                None
+            }
+        }
+        Requirement::Loadout => None,
+    }
+}
+
+struct Lines {
+    pool: Option<usize>,
+    missions: Vec<Option<usize>>,
+}
+
+// serde_json doesn't keep spans, so find each mission by its quoted name after the pool key
+fn locate(source: &str, key: &str, missions: &[Mission]) -> Lines {
+    let pool_offset = source.find(&format!("\"{}\"", key));
+    let mut cursor = pool_offset.unwrap_or(0);
+    let missions = missions.iter().map(|mission| {
+        let quoted = serde_json::to_string(&mission.name).ok()?;
+        let offset = cursor + source[cursor..].find(&quoted)?;
+        cursor = offset + quoted.len();
+        Some(line_of(source, offset))
+    }).collect();
+
+    Lines { pool: pool_offset.map(|o| line_of(source, o)), missions }
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use crate::state::load_catalog;
+
+    fn catalog() -> Catalog {
+        load_catalog(&Path::new(env!("CARGO_MANIFEST_DIR")).join("catalog.json")).unwrap()
+    }
+
+    // Every pool gets one plain mission unless `extra` says otherwise, one per line
+    fn source(missions: &str, coop_granprix: &str, coop_single: &str) -> String {
+        let plain = |name: &str| format!("{{ \"name\": \"{}\", \"description\": \"-\" }}", name);
+        let pool = |extra: &str, name: &str| if extra.is_empty() { plain(name) } else { format!("{},\n    {}", plain(name), extra) };
+        format!(
+            "{{\n  \"missions\": [\n    {}\n  ],\n  \"coop_granprix\": [\n    {}\n  ],\n  \"coop_single\": [\n    {}\n  ]\n}}\n",
+            pool(missions, "Solo"),
+            pool(coop_granprix, "Granprix"),
+            pool(coop_single, "Single"),
+        )
+    }
+
+    fn issues(source: &str) -> Vec<String> {
+        validate(source, &catalog()).err().unwrap_or_default().iter().map(|i| i.to_string()).collect()
+    }
+
+    #[test]
+    fn plain_missions_pass() {
+        assert_eq!(issues(&source("", "", "")), Vec::<String>::new());
+    }
+
+    #[test]
+    fn duplicate_names_across_pools_point_at_both_lines() {
+        let source = source("", "{ \"name\": \"solo\", \"description\": \"-\" }", "");
+        assert_eq!(issues(&source), ["line 7: solo: duplicate name, already used in missions on line 3"]);
+    }
+
+    #[test]
+    fn empty_random_number_range() {
+        let source = source("{ \"name\": \"Dice\", \"description\": \"-\", \"needs_random_number\": 0 }", "", "");
+        assert_eq!(issues(&source), ["line 4: Dice: number range 1..=0 is empty"]);
+    }
+
+    #[test]
+    fn gacha_checklist_outside_coop_single() {
+        let gacha = "{ \"name\": \"Gacha\", \"description\": \"-\", \"needs_gacha_item_checklist\": true }";
+        assert_eq!(issues(&source("", gacha, "")), ["line 7: Gacha: gacha item checklists only belong in coop_single"]);
+        assert_eq!(issues(&source("", "", gacha)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn syntax_errors_keep_their_line() {
+        let source = source("", "", "").replacen("\"Granprix\",", "\"Granprix\"", 1);
+        let issues = issues(&source);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].starts_with("line 6: "), "{}", issues[0]);
+    }
+}